@@ -0,0 +1,490 @@
+mod stats;
+
+use std::{fs, path::PathBuf, thread};
+
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use runes_core::{
+    batch::{run_jobs, seat_seeds},
+    game::{Game, Move},
+    player_spec::PlayerSpec,
+    record::GameRecord,
+    session::Session,
+    setup::{parse_setups, scatter, Setup},
+};
+use stats::{Decision, Score, Sprt};
+
+/// Where the games start from, before the random opening.
+#[derive(Clone, Debug, PartialEq)]
+enum Start {
+    Empty,
+    /// This many random Births and Gifts, a new position for every pair of games.
+    Scatter(usize),
+    /// The setups of a file, one after the other for the pairs of games.
+    Setups(Vec<Setup>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Options {
+    players: Vec<PlayerSpec>,
+    /// The first player against each of the others instead of everyone against everyone.
+    gauntlet: bool,
+    /// Games per pairing, the first player alternating.
+    games: usize,
+    board_size: usize,
+    /// Random plies played before the players take over, the same for both games of a pair.
+    opening_plies: usize,
+    start: Start,
+    /// Play with the pie rule; only an opening of at most one ply leaves the swap to a player.
+    pie: bool,
+    threads: usize,
+    seed: u64,
+    records: Option<PathBuf>,
+    sprt: Option<Sprt>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            players: Vec::new(),
+            gauntlet: false,
+            games: 20,
+            board_size: 9,
+            opening_plies: 2,
+            start: Start::Empty,
+            pie: false,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            seed: 0,
+            records: None,
+            sprt: None,
+        }
+    }
+}
+
+const USAGE: &str = "Usage: runes_arena [--gauntlet] [--games N] [--size N] [--opening N] \
+[--setup scatter:N|FILE] [--pie] [--threads N] [--seed N] [--records DIR] [--sprt ELO0,ELO1] PLAYER PLAYER...
+Players: random, negamax[:LEVEL], casual[:LEVEL], mcts[:LEVEL|:TIMEms], engine:PROGRAM[@TIMEms]";
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {arg}"));
+        let number = |v: &String| {
+            v.parse::<u64>()
+                .map_err(|_| format!("Invalid number `{v}`"))
+        };
+        match arg.as_str() {
+            "--gauntlet" => options.gauntlet = true,
+            "--games" => options.games = number(value()?)?.max(1) as usize,
+            "--size" => options.board_size = number(value()?)? as usize,
+            "--opening" => options.opening_plies = number(value()?)? as usize,
+            "--setup" => options.start = parse_start(value()?)?,
+            "--pie" => options.pie = true,
+            "--threads" => options.threads = number(value()?)?.max(1) as usize,
+            "--seed" => options.seed = number(value()?)?,
+            "--records" => options.records = Some(PathBuf::from(value()?)),
+            "--sprt" => {
+                let v = value()?;
+                let elo = v
+                    .split_once(',')
+                    .and_then(|(e0, e1)| Some((e0.parse().ok()?, e1.parse().ok()?)))
+                    .ok_or(format!("Invalid SPRT bounds `{v}`, expected ELO0,ELO1"))?;
+                options.sprt = Some(Sprt {
+                    elo0: elo.0,
+                    elo1: elo.1,
+                    alpha: 0.05,
+                    beta: 0.05,
+                });
+            }
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {arg}\n{USAGE}")),
+            _ => options.players.push(arg.parse()?),
+        }
+    }
+    if options.players.len() < 2 {
+        return Err(format!("At least two players are needed\n{USAGE}"));
+    }
+    if options.sprt.is_some() && options.players.len() != 2 {
+        return Err("SPRT compares exactly two players".to_string());
+    }
+    Ok(options)
+}
+
+fn parse_start(value: &str) -> Result<Start, String> {
+    if let Some(count) = value.strip_prefix("scatter:") {
+        let count = count
+            .parse()
+            .map_err(|_| format!("Invalid number `{count}`"))?;
+        return Ok(Start::Scatter(count));
+    }
+    let text = fs::read_to_string(value).map_err(|e| format!("Can't read {value}: {e}"))?;
+    let setups = parse_setups(&text).map_err(|e| format!("{value}: {e}"))?;
+    if setups.is_empty() {
+        return Err(format!("No setups in {value}"));
+    }
+    Ok(Start::Setups(setups))
+}
+
+/// One game to play: `first` moves first, both are indices into `Options::players`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Job {
+    index: usize,
+    first: usize,
+    second: usize,
+    /// Both games of a pair start from the same position.
+    pair: usize,
+    /// Seeds the opening and, through `seat_seeds`, the players; both games of a pair get the
+    /// same one.
+    seed: u64,
+}
+
+#[derive(Clone, Debug)]
+struct GameResult {
+    job: Job,
+    /// Index into `Options::players`.
+    winner: Option<usize>,
+    record: GameRecord,
+}
+
+fn pairings(options: &Options) -> Vec<(usize, usize)> {
+    let n = options.players.len();
+    if options.gauntlet {
+        (1..n).map(|j| (0, j)).collect()
+    } else {
+        (0..n)
+            .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+            .collect()
+    }
+}
+
+/// The games in the order they are played: round after round over all pairings, so an early
+/// stop leaves every pairing with about the same number of games.
+fn schedule(options: &Options) -> Vec<Job> {
+    let pairings = pairings(options);
+    let mut jobs = Vec::new();
+    for game in 0..options.games {
+        for (p, &(a, b)) in pairings.iter().enumerate() {
+            let (first, second) = if game % 2 == 0 { (a, b) } else { (b, a) };
+            let pair = game / 2 * pairings.len() + p;
+            jobs.push(Job {
+                index: jobs.len(),
+                first,
+                second,
+                pair,
+                seed: options.seed.wrapping_add(pair as u64),
+            });
+        }
+    }
+    jobs
+}
+
+/// The position a game of `job` starts from, before the opening.
+fn start_position(options: &Options, job: Job) -> Result<Game, String> {
+    let mut game = match &options.start {
+        Start::Empty => Game::new(options.board_size),
+        Start::Scatter(count) => scatter(options.board_size, *count, job.seed)?,
+        Start::Setups(setups) => setups[job.pair % setups.len()].game.clone(),
+    };
+    game.pie_rule |= options.pie;
+    Ok(game)
+}
+
+fn random_opening(start: &Game, plies: usize, seed: u64) -> Vec<Move> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    loop {
+        let mut game = start.clone();
+        let mut moves = Vec::new();
+        while moves.len() < plies && !game.game_over {
+            let Some(&m) = game.generate_moves().choose(&mut rng) else {
+                break;
+            };
+            game.apply_move(m).unwrap();
+            moves.push(m);
+        }
+        if !game.game_over {
+            return moves;
+        }
+    }
+}
+
+fn play(options: &Options, job: Job) -> Result<GameResult, String> {
+    let start = start_position(options, job)?;
+    let opening = random_opening(&start, options.opening_plies, job.seed);
+    let mut game = start.clone();
+    for &m in &opening {
+        game.apply_move(m)?;
+    }
+    // Each seat gets its own random numbers, also when a player meets itself.
+    let [first_seed, second_seed] = seat_seeds(job.seed);
+    let first = options.players[job.first].create(Some(first_seed))?;
+    let second = options.players[job.second].create(Some(second_seed))?;
+    let mut session = Session::with_game(first, second, game);
+
+    // What each player thought of its moves, logged as comments of the record.
+    let mut comments = Vec::new();
+    let mut log = |s: &Session| {
+        if let Some(last) = s.moves.len().checked_sub(1) {
+            let mover = &s.players[1 - s.game.next_player as usize];
+            comments.resize(last, String::new());
+            comments.push(mover.search_info().map_or(String::new(), |i| i.to_string()));
+        }
+    };
+    session.start_loop(&mut log);
+    log(&session);
+
+    let mut record = GameRecord::from_session(&session);
+    let played = std::mem::take(&mut record.moves);
+    record.moves = opening.iter().chain(&played).copied().collect();
+    record.comments = vec![String::new(); opening.len()];
+    record.comments.extend(comments);
+    record.comments.resize(record.moves.len(), String::new());
+    // The record starts before the opening.
+    record.headers.retain(|(key, _)| key != "Start");
+    if let Some(start) = GameRecord::new(&start, Vec::new()).header("Start") {
+        record.set_header("Start", start);
+    }
+    // After a swap the second player ended up with Wealth.
+    let (wealth, knowledge) = if session.swapped {
+        (job.second, job.first)
+    } else {
+        (job.first, job.second)
+    };
+    record.set_header("Wealth", &options.players[wealth].to_string());
+    record.set_header("Knowledge", &options.players[knowledge].to_string());
+    record.set_header("Game", &(job.index + 1).to_string());
+    record.set_header("Seed", &job.seed.to_string());
+    record.set_header("Opening", &format!("{} plies", opening.len()));
+    match &options.start {
+        Start::Empty => (),
+        Start::Scatter(count) => record.set_header("Setup", &format!("scatter:{count}")),
+        Start::Setups(setups) => record.set_header("Setup", &setups[job.pair % setups.len()].name),
+    }
+
+    let winner = session
+        .winner_index()
+        .map(|w| if w == 0 { wealth } else { knowledge });
+    Ok(GameResult {
+        job,
+        winner,
+        record,
+    })
+}
+
+/// Score of player `a` against player `b` in `results`.
+fn score(results: &[GameResult], a: usize, b: usize) -> Score {
+    let mut score = Score::default();
+    for r in results {
+        let players = [r.job.first, r.job.second];
+        if !players.contains(&a) || !players.contains(&b) {
+            continue;
+        }
+        match r.winner {
+            Some(w) if w == a => score.wins += 1,
+            Some(_) => score.losses += 1,
+            None => score.draws += 1,
+        }
+    }
+    score
+}
+
+/// Plays the games of `options` on `options.threads` threads and calls `report` after each.
+/// With SPRT the games stop once it decides; the games already running are still finished.
+fn run(
+    options: &Options,
+    report: impl Fn(&GameResult, usize) + Sync,
+) -> Result<Vec<GameResult>, String> {
+    let jobs = schedule(options);
+    run_jobs(
+        jobs.len(),
+        options.threads,
+        |i| play(options, jobs[i]),
+        |results| {
+            report(results.last().unwrap(), jobs.len());
+            let decided = options
+                .sprt
+                .as_ref()
+                .is_some_and(|sprt| sprt.decide(&score(results, 0, 1)) != Decision::Continue);
+            !decided
+        },
+    )
+}
+
+fn format_elo((elo, margin): (f64, f64)) -> String {
+    format!("{elo:+.0} ± {margin:.0}")
+}
+
+fn summary(options: &Options, results: &[GameResult]) -> String {
+    let mut lines = vec![format!("{} games", results.len())];
+    lines.push(String::new());
+    for (a, b) in pairings(options) {
+        let s = score(results, a, b);
+        lines.push(format!(
+            "{} vs {}: +{} ={} -{}, score {:.1}%, Elo {}",
+            options.players[a],
+            options.players[b],
+            s.wins,
+            s.draws,
+            s.losses,
+            s.mean() * 100.0,
+            format_elo(s.elo())
+        ));
+    }
+    lines.push(String::new());
+    lines.push("Standings (Elo against the player's opponents):".to_string());
+    let mut standings: Vec<(usize, Score)> = (0..options.players.len())
+        .map(|p| {
+            let mut total = Score::default();
+            for (a, b) in pairings(options) {
+                if a == p {
+                    total.add(&score(results, a, b));
+                } else if b == p {
+                    total.add(&score(results, a, b).reversed());
+                }
+            }
+            (p, total)
+        })
+        .collect();
+    standings.sort_by(|x, y| y.1.mean().total_cmp(&x.1.mean()));
+    for (rank, (p, s)) in standings.iter().enumerate() {
+        lines.push(format!(
+            "{:>2}. {:<24} {:>5.1}/{:<4} Elo {}",
+            rank + 1,
+            options.players[*p].to_string(),
+            s.wins as f64 + s.draws as f64 / 2.0,
+            s.games(),
+            format_elo(s.elo())
+        ));
+    }
+    if let Some(sprt) = &options.sprt {
+        let s = score(results, 0, 1);
+        let (lower, upper) = sprt.bounds();
+        let verdict = match sprt.decide(&s) {
+            Decision::H1 => format!("H1 accepted: {} is stronger", options.players[0]),
+            Decision::H0 => format!("H0 accepted: {} is not stronger", options.players[0]),
+            Decision::Continue => "no decision yet".to_string(),
+        };
+        lines.push(String::new());
+        lines.push(format!(
+            "SPRT elo0={} elo1={}: LLR {:.2} ({:.2}, {:.2}), {verdict}",
+            sprt.elo0,
+            sprt.elo1,
+            sprt.llr(&s),
+            lower,
+            upper
+        ));
+    }
+    lines.join("\n")
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    };
+    if let Some(dir) = &options.records {
+        if let Err(e) = fs::create_dir_all(dir) {
+            eprintln!("Can't create {}: {e}", dir.display());
+            std::process::exit(1);
+        }
+    }
+    let results = run(&options, |result, total| {
+        let record = &result.record;
+        println!(
+            "Game {}/{total}: {} vs {}, {} ({}, {} plies)",
+            result.job.index + 1,
+            options.players[result.job.first],
+            options.players[result.job.second],
+            record.header("Result").unwrap_or("*"),
+            record.header("Termination").unwrap_or("unfinished"),
+            record.moves.len()
+        );
+        if let Some(dir) = &options.records {
+            let path = dir.join(format!("game-{:04}.txt", result.job.index + 1));
+            if let Err(e) = fs::write(&path, record.to_string()) {
+                eprintln!("Can't write {}: {e}", path.display());
+            }
+        }
+    });
+    match results {
+        Ok(results) => println!("\n{}", summary(&options, &results)),
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(args: &str) -> Options {
+        let args: Vec<String> = args.split(' ').map(String::from).collect();
+        parse_args(&args).unwrap()
+    }
+
+    #[test]
+    fn round_robin_alternates_first_player() {
+        let options = options("--games 2 --size 7 --threads 2 random random negamax:easy");
+        let results = run(&options, |_, _| {}).unwrap();
+        assert_eq!(results.len(), 6);
+        for pair in results.chunks(3).collect::<Vec<_>>().windows(2) {
+            for (a, b) in pair[0].iter().zip(pair[1]) {
+                assert_eq!((a.job.first, a.job.second), (b.job.second, b.job.first));
+                assert_eq!(a.record.moves[..2], b.record.moves[..2]);
+            }
+        }
+        for r in &results {
+            let parsed: GameRecord = r.record.to_string().parse().unwrap();
+            assert_eq!(&parsed, &r.record);
+            assert!(parsed.replay().unwrap().game_over);
+        }
+    }
+
+    #[test]
+    fn scattered_start() {
+        let options = options("--games 2 --size 7 --opening 0 --setup scatter:6 random random");
+        let results = run(&options, |_, _| {}).unwrap();
+        let start = results[0].record.header("Start").unwrap();
+        assert_eq!(results[1].record.header("Start"), Some(start));
+        let game = results[0].record.start().unwrap();
+        assert_eq!(game.board.notation().matches('.').count(), 49 - 6);
+        assert!(results[0].record.replay().unwrap().game_over);
+    }
+
+    #[test]
+    fn elo_and_sprt() {
+        let score = Score {
+            wins: 60,
+            draws: 20,
+            losses: 20,
+        };
+        let (elo, margin) = score.elo();
+        assert_eq!(elo.round(), 147.0);
+        assert!(margin > 50.0 && margin < 100.0);
+        let sprt = Sprt {
+            elo0: 0.0,
+            elo1: 50.0,
+            alpha: 0.05,
+            beta: 0.05,
+        };
+        assert_eq!(sprt.decide(&score), Decision::H1);
+        assert_eq!(sprt.decide(&score.reversed()), Decision::H0);
+        let close = Score {
+            wins: 50,
+            draws: 0,
+            losses: 46,
+        };
+        assert_eq!(sprt.decide(&close), Decision::Continue);
+        let few = Score {
+            wins: 8,
+            draws: 0,
+            losses: 0,
+        };
+        assert_eq!(sprt.decide(&few), Decision::Continue);
+    }
+}
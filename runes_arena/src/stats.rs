@@ -0,0 +1,130 @@
+/// Quantile of the normal distribution for 95% confidence intervals.
+const Z_95: f64 = 1.96;
+
+/// SPRT doesn't decide on fewer games, the normal approximation would be too rough.
+const MIN_SPRT_GAMES: u32 = 16;
+
+/// Results of a series of games from the point of view of one player.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Score {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl Score {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// The same games seen from the opponent.
+    pub fn reversed(&self) -> Score {
+        Score {
+            wins: self.losses,
+            draws: self.draws,
+            losses: self.wins,
+        }
+    }
+
+    pub fn add(&mut self, other: &Score) {
+        self.wins += other.wins;
+        self.draws += other.draws;
+        self.losses += other.losses;
+    }
+
+    /// Points per game: 1 for a win, 0.5 for a draw.
+    pub fn mean(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games().max(1) as f64
+    }
+
+    /// Variance of the points of a single game.
+    fn variance(&self) -> f64 {
+        let mean = self.mean();
+        let n = self.games().max(1) as f64;
+        (self.wins as f64 * (1.0 - mean).powi(2)
+            + self.draws as f64 * (0.5 - mean).powi(2)
+            + self.losses as f64 * mean.powi(2))
+            / n
+    }
+
+    /// Elo difference to the opponents and the half-width of its 95% confidence interval.
+    /// Without losses (or wins) the difference is infinite.
+    pub fn elo(&self) -> (f64, f64) {
+        let margin = Z_95 * (self.variance() / self.games().max(1) as f64).sqrt();
+        let mean = self.mean();
+        let low = elo_from_score((mean - margin).max(0.0));
+        let high = elo_from_score((mean + margin).min(1.0));
+        let margin = (high - low) / 2.0;
+        (
+            elo_from_score(mean),
+            if margin.is_nan() {
+                f64::INFINITY
+            } else {
+                margin
+            },
+        )
+    }
+}
+
+/// Elo difference at which the stronger player expects `score` points per game.
+pub fn elo_from_score(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+pub fn score_from_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// Sequential probability ratio test: is the first player `elo1` stronger (H1) or only `elo0`
+/// stronger (H0)? `alpha` and `beta` are the chances to wrongly accept H1 and H0.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Decision {
+    H0,
+    H1,
+    Continue,
+}
+
+impl Sprt {
+    /// Log-likelihood ratio of H1 against H0, with the normal approximation of the results.
+    /// It stays 0 while all games ended the same, which says nothing about their spread.
+    pub fn llr(&self, score: &Score) -> f64 {
+        let s0 = score_from_elo(self.elo0);
+        let s1 = score_from_elo(self.elo1);
+        let variance = score.variance();
+        if variance == 0.0 {
+            return 0.0;
+        }
+        let n = score.games() as f64;
+        n * (s1 - s0) * (2.0 * score.mean() - s0 - s1) / (2.0 * variance)
+    }
+
+    /// The LLR at which H0 and H1 are accepted.
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    pub fn decide(&self, score: &Score) -> Decision {
+        let llr = self.llr(score);
+        let (lower, upper) = self.bounds();
+        if score.games() < MIN_SPRT_GAMES {
+            Decision::Continue
+        } else if llr >= upper {
+            Decision::H1
+        } else if llr <= lower {
+            Decision::H0
+        } else {
+            Decision::Continue
+        }
+    }
+}
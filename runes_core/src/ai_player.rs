@@ -1,11 +1,33 @@
-use minimax::{MCTSOptions, MonteCarloTreeSearch, Negamax, Random, Strategy};
-use std::{cell::RefCell, time::Duration};
+use minimax::Strategy;
+use rand::{
+    distributions::WeightedIndex, prelude::Distribution, seq::SliceRandom, Rng, SeedableRng,
+};
+use rand_chacha::ChaCha8Rng;
+use std::{
+    cell::RefCell,
+    fmt::Display,
+    str::FromStr,
+    sync::{atomic::AtomicBool, Arc},
+    time::{Duration, Instant},
+};
 
 use crate::{
     board::{Field, Position},
     game::{Game, Move, Player},
+    mcts::{Mcts, MctsOptions},
+    search::{AlphaBeta, SearchInfo},
+    solver,
 };
 
+/// How many of its own moves an AI looks ahead for a forced Joy before the main search.
+pub(crate) const FORCED_JOY_DEPTH: usize = 3;
+
+const EVAL_LIMIT: i32 = 10_000;
+
+/// Rollouts per `Level` of a deterministic `AiPlayerMonte`, about what one thread manages in
+/// the time a normal one gets.
+const ROLLOUTS_PER_LEVEL: u32 = 2_000;
+
 #[repr(u8)]
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum Level {
@@ -15,18 +37,61 @@ pub enum Level {
     VeryHard = 4,
 }
 
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "easy" => Ok(Level::Easy),
+            "medium" => Ok(Level::Medium),
+            "hard" => Ok(Level::Hard),
+            "veryhard" => Ok(Level::VeryHard),
+            _ => Err(format!("Unknown level `{s}`")),
+        }
+    }
+}
+
+/// A fresh random seed unless `seed` is given.
+fn rng_from(seed: Option<u64>) -> ChaCha8Rng {
+    match seed {
+        Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+        None => ChaCha8Rng::from_entropy(),
+    }
+}
+
+/// The pie rule decision of a searching player: swap when the best move it found, `m`, scores
+/// below even, so the side that just moved is the better one to have.
+fn take_pie(game: &Game, m: Move, info: &RefCell<Option<SearchInfo>>) -> Move {
+    let mut info = info.borrow_mut();
+    match info.as_mut() {
+        Some(info) if game.can_swap() && info.score < 0 => {
+            info.score = -info.score;
+            info.pv = vec![Move::SWAP];
+            Move::SWAP
+        }
+        _ => m,
+    }
+}
+
 pub struct AiPlayerRandom {
     pub symbol: Field,
     pub level: Level,
-    strategy: RefCell<Random<Game>>,
+    rng: RefCell<ChaCha8Rng>,
+    info: RefCell<Option<SearchInfo>>,
 }
 
 impl AiPlayerRandom {
     pub fn new(level: Level) -> Self {
+        AiPlayerRandom::with_seed(level, None)
+    }
+
+    /// With a seed the player makes the same moves every time it plays the same game.
+    pub fn with_seed(level: Level, seed: Option<u64>) -> Self {
         AiPlayerRandom {
             symbol: Field::Empty,
             level,
-            strategy: RefCell::new(Random::new()),
+            rng: RefCell::new(rng_from(seed)),
+            info: RefCell::new(None),
         }
     }
 }
@@ -37,32 +102,77 @@ impl Player for AiPlayerRandom {
     }
 
     fn make_move(&self, game: Game) -> Move {
-        self.strategy.borrow_mut().choose_move(&game).unwrap()
+        let moves = game.generate_moves();
+        let mut rng = self.rng.borrow_mut();
+        let m = if game.can_swap() && rng.gen_bool(0.5) {
+            Move::SWAP
+        } else {
+            *moves.choose(&mut *rng).unwrap()
+        };
+        *self.info.borrow_mut() = Some(SearchInfo {
+            score: 0,
+            depth: Some(0),
+            playouts: None,
+            nodes: moves.len() as u64,
+            elapsed: Duration::ZERO,
+            pv: vec![m],
+        });
+        m
     }
 
     fn name(&self) -> String {
         format!("AI Dumb {}", self.symbol)
     }
+
+    fn search_info(&self) -> Option<SearchInfo> {
+        self.info.borrow().clone()
+    }
 }
 
 pub struct AiPlayerMonte {
     pub symbol: Field,
     pub level: Level,
-    strategy: RefCell<MonteCarloTreeSearch<Game>>,
+    strategy: RefCell<Mcts>,
+    deterministic: bool,
+    info: RefCell<Option<SearchInfo>>,
 }
 
 impl AiPlayerMonte {
     pub fn new(level: Level) -> Self {
-        let mut mcts = MonteCarloTreeSearch::new(
-            MCTSOptions::default()
-                .with_max_rollout_depth(5)
-                .with_num_threads(4),
-        );
-        mcts.set_timeout(Duration::from_millis(level as u64 * 300));
+        AiPlayerMonte::with_seed(level, None)
+    }
+
+    /// Searches on several threads for a fixed time, so a seed only fixes where the
+    /// random numbers start. Use `deterministic` to reproduce moves.
+    pub fn with_seed(level: Level, seed: Option<u64>) -> Self {
+        AiPlayerMonte::with_options(
+            level,
+            MctsOptions {
+                timeout: Duration::from_millis(level as u64 * 300),
+                seed,
+                ..MctsOptions::default()
+            },
+        )
+    }
+
+    /// Searches on one thread for a fixed number of rollouts, afresh for every move, and never
+    /// ponders: the same seed and position always give the same move.
+    pub fn deterministic(level: Level, seed: u64) -> Self {
+        let options = MctsOptions::deterministic(seed, level as u32 * ROLLOUTS_PER_LEVEL);
+        AiPlayerMonte {
+            deterministic: true,
+            ..AiPlayerMonte::with_options(level, options)
+        }
+    }
+
+    /// Searches as `options` say, whatever the level.
+    pub fn with_options(level: Level, options: MctsOptions) -> Self {
         AiPlayerMonte {
             symbol: Field::Empty,
             level,
-            strategy: RefCell::new(mcts),
+            strategy: RefCell::new(Mcts::new(options)),
+            deterministic: false,
+            info: RefCell::new(None),
         }
     }
 }
@@ -73,26 +183,69 @@ impl Player for AiPlayerMonte {
     }
 
     fn make_move(&self, game: Game) -> Move {
-        self.strategy.borrow_mut().choose_move(&game).unwrap()
+        let started = Instant::now();
+        if let Some(line) = solver::forced_joy(&game, FORCED_JOY_DEPTH) {
+            self.stop_pondering();
+            let m = line[0];
+            *self.info.borrow_mut() = Some(SearchInfo::forced_joy(line, started.elapsed()));
+            return m;
+        }
+        let mut strategy = self.strategy.borrow_mut();
+        let m = strategy.choose_move(&game).unwrap();
+        *self.info.borrow_mut() = Some(SearchInfo {
+            score: strategy.root_stats()[0].score(),
+            depth: None,
+            playouts: Some(strategy.rollouts()),
+            nodes: strategy.tree_size() as u64,
+            elapsed: started.elapsed(),
+            pv: strategy.principal_variation(),
+        });
+        take_pie(&game, m, &self.info)
     }
 
     fn name(&self) -> String {
         format!("AI Monte {}", self.symbol)
     }
+
+    fn ponder(&self, game: Game) {
+        if !self.deterministic {
+            self.strategy.borrow_mut().ponder(&game);
+        }
+    }
+
+    fn stop_pondering(&self) {
+        self.strategy.borrow_mut().stop_pondering();
+    }
+
+    fn stop_flag(&self) -> Option<Arc<AtomicBool>> {
+        Some(self.strategy.borrow().stop_flag())
+    }
+
+    fn search_info(&self) -> Option<SearchInfo> {
+        self.info.borrow().clone()
+    }
 }
 
+/// Alpha-beta search without any randomness: the same game always gets the same moves,
+/// so it needs no seed.
 pub struct AiPlayer {
     pub symbol: Field,
     pub level: Level,
-    strategy: RefCell<Negamax<Eval>>,
+    strategy: RefCell<AlphaBeta<Eval>>,
+    info: RefCell<Option<SearchInfo>>,
 }
 
 impl AiPlayer {
     pub fn new(level: Level) -> Self {
+        AiPlayer::with_weights(level, Weights::default())
+    }
+
+    pub fn with_weights(level: Level, weights: Weights) -> Self {
         AiPlayer {
             symbol: Field::Empty,
             level,
-            strategy: RefCell::new(Negamax::new(Eval::default(), level as u8)),
+            strategy: RefCell::new(AlphaBeta::new(Eval::new(weights), level as u8)),
+            info: RefCell::new(None),
         }
     }
 }
@@ -103,14 +256,203 @@ impl Player for AiPlayer {
     }
 
     fn make_move(&self, game: Game) -> Move {
+        let started = Instant::now();
+        if let Some(line) = solver::forced_joy(&game, FORCED_JOY_DEPTH) {
+            let m = line[0];
+            *self.info.borrow_mut() = Some(SearchInfo::forced_joy(line, started.elapsed()));
+            return m;
+        }
         let mut strategy = self.strategy.borrow_mut();
-        let ai_move = strategy.choose_move(&game);
-        ai_move.unwrap()
+        let ai_move = strategy.choose_move(&game).unwrap();
+        *self.info.borrow_mut() = Some(SearchInfo {
+            score: strategy.root_value(),
+            depth: Some(strategy.max_depth()),
+            playouts: None,
+            nodes: strategy.nodes(),
+            elapsed: started.elapsed(),
+            pv: strategy.principal_variation(),
+        });
+        take_pie(&game, ai_move, &self.info)
     }
 
     fn name(&self) -> String {
         format!("AI Max {}", self.symbol)
     }
+
+    fn stop_flag(&self) -> Option<Arc<AtomicBool>> {
+        Some(self.strategy.borrow().stop_flag())
+    }
+
+    fn search_info(&self) -> Option<SearchInfo> {
+        self.info.borrow().clone()
+    }
+}
+
+/// How a `Level` of `AiPlayerCasual` makes mistakes.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Difficulty {
+    /// Search depth in plies.
+    pub depth: u8,
+    /// The move is picked from this many of the best scoring ones.
+    pub top_n: usize,
+    /// Higher values make worse moves among the `top_n` more likely, 0 always picks the best.
+    pub temperature: f64,
+    /// Chance to play as if the opponent had no Joy squares.
+    pub overlook_threats: f64,
+    /// How many of its own moves it looks ahead for a forced Joy, 0 for none.
+    pub forced_joy_depth: usize,
+}
+
+impl Level {
+    /// Calibrated with 200 games on a 9x9 board against `AiPlayer::new(Level::Medium)`, in
+    /// pairs from the same two random plies with the first player alternating. Win rates
+    /// count a full board as half a win. Measure them again with
+    /// `cargo test --release -p runes_core -- --ignored casual_levels --nocapture`.
+    pub fn difficulty(&self) -> Difficulty {
+        match self {
+            // Target 10%, measured 7.8%.
+            Level::Easy => Difficulty {
+                depth: 1,
+                top_n: 2,
+                temperature: 1.0,
+                overlook_threats: 0.3,
+                forced_joy_depth: 1,
+            },
+            // Target 30%, measured 27.5%.
+            Level::Medium => Difficulty {
+                depth: 2,
+                top_n: 2,
+                temperature: 1.0,
+                overlook_threats: 0.15,
+                forced_joy_depth: 2,
+            },
+            // Target 40%, measured 39.0%.
+            Level::Hard => Difficulty {
+                depth: 2,
+                top_n: 2,
+                temperature: 0.5,
+                overlook_threats: 0.1,
+                forced_joy_depth: FORCED_JOY_DEPTH,
+            },
+            // Target 95%, measured 68.2%, the strongest setting found. Most games it doesn't win
+            // fill the board. Depth 4 measured no better over 20 games, a longer look for forced
+            // Joys and other weights neither, and depth 5 takes minutes a game.
+            Level::VeryHard => Difficulty {
+                depth: 3,
+                top_n: 1,
+                temperature: 0.0,
+                overlook_threats: 0.0,
+                forced_joy_depth: FORCED_JOY_DEPTH,
+            },
+        }
+    }
+}
+
+/// An AI that plays like a person of the given `Level`: it doesn't always pick its best move,
+/// sometimes misses the opponent's threats and doesn't look far ahead.
+pub struct AiPlayerCasual {
+    pub symbol: Field,
+    pub level: Level,
+    difficulty: Difficulty,
+    search: RefCell<AlphaBeta<Eval>>,
+    blind: AlphaBeta<Eval>,
+    rng: RefCell<ChaCha8Rng>,
+    info: RefCell<Option<SearchInfo>>,
+}
+
+impl AiPlayerCasual {
+    pub fn new(level: Level) -> Self {
+        AiPlayerCasual::with_seed(level, None)
+    }
+
+    /// With a seed the player makes the same moves, mistakes included, every time it plays
+    /// the same game.
+    pub fn with_seed(level: Level, seed: Option<u64>) -> Self {
+        AiPlayerCasual::with_difficulty(level, level.difficulty(), seed)
+    }
+
+    pub fn with_difficulty(level: Level, difficulty: Difficulty, seed: Option<u64>) -> Self {
+        // Ignoring the Joy squares of the side to move after our move is ignoring the opponent's.
+        let blind_weights = Weights {
+            own_joy_squares: 0,
+            ..Weights::default()
+        };
+        AiPlayerCasual {
+            symbol: Field::Empty,
+            level,
+            difficulty,
+            search: RefCell::new(AlphaBeta::new(Eval::default(), difficulty.depth)),
+            blind: AlphaBeta::new(Eval::new(blind_weights), 1),
+            rng: RefCell::new(rng_from(seed)),
+            info: RefCell::new(None),
+        }
+    }
+}
+
+impl Player for AiPlayerCasual {
+    fn set_symbol(&mut self, symbol: Field) {
+        self.symbol = symbol;
+    }
+
+    fn make_move(&self, game: Game) -> Move {
+        let started = Instant::now();
+        if let Some(line) = solver::forced_joy(&game, self.difficulty.forced_joy_depth) {
+            let m = line[0];
+            *self.info.borrow_mut() = Some(SearchInfo::forced_joy(line, started.elapsed()));
+            return m;
+        }
+        let mut rng = self.rng.borrow_mut();
+        let blind = rng.gen_bool(self.difficulty.overlook_threats);
+        if !blind && self.difficulty.top_n <= 1 {
+            // Only the best move counts, which the pruned search finds much faster.
+            let mut search = self.search.borrow_mut();
+            let m = search.choose_move(&game).unwrap();
+            *self.info.borrow_mut() = Some(SearchInfo {
+                score: search.root_value(),
+                depth: Some(search.max_depth()),
+                playouts: None,
+                nodes: search.nodes(),
+                elapsed: started.elapsed(),
+                pv: search.principal_variation(),
+            });
+            return take_pie(&game, m, &self.info);
+        }
+        let full = self.search.borrow();
+        let (search, moves) = if blind {
+            (&self.blind, game.candidate_moves())
+        } else {
+            (&*full, game.generate_moves())
+        };
+        let scored = search.score_lines(&game, moves);
+        let top = &scored[..scored.len().min(self.difficulty.top_n.max(1))];
+        let idx = if self.difficulty.temperature <= 0.0 {
+            0
+        } else {
+            let best = top[0].0 as f64;
+            let weights = top
+                .iter()
+                .map(|(value, _)| ((*value as f64 - best) / self.difficulty.temperature).exp());
+            WeightedIndex::new(weights).map_or(0, |w| w.sample(&mut *rng))
+        };
+        let (score, pv) = top[idx].clone();
+        *self.info.borrow_mut() = Some(SearchInfo {
+            score,
+            depth: Some(search.max_depth()),
+            playouts: None,
+            nodes: search.nodes(),
+            elapsed: started.elapsed(),
+            pv,
+        });
+        take_pie(&game, top[idx].1[0], &self.info)
+    }
+
+    fn name(&self) -> String {
+        format!("AI Casual {}", self.symbol)
+    }
+
+    fn search_info(&self) -> Option<SearchInfo> {
+        self.info.borrow().clone()
+    }
 }
 
 impl minimax::Game for Game {
@@ -137,8 +479,100 @@ impl minimax::Game for Game {
         }
     }
 }
+/// How much each feature of a position is worth to `Eval`, seen from the player to move.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Weights {
+    pub own_joy_squares: i16,
+    pub opponent_joy_squares: i16,
+    pub own_runes: i16,
+    pub opponent_runes: i16,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            own_joy_squares: 3,
+            opponent_joy_squares: -1,
+            own_runes: 0,
+            opponent_runes: 0,
+        }
+    }
+}
+
+impl Weights {
+    pub const NAMES: [&'static str; 4] = [
+        "own_joy_squares",
+        "opponent_joy_squares",
+        "own_runes",
+        "opponent_runes",
+    ];
+
+    pub fn to_vec(&self) -> Vec<i16> {
+        vec![
+            self.own_joy_squares,
+            self.opponent_joy_squares,
+            self.own_runes,
+            self.opponent_runes,
+        ]
+    }
+
+    pub fn from_slice(values: &[i16]) -> Self {
+        Weights {
+            own_joy_squares: values[0],
+            opponent_joy_squares: values[1],
+            own_runes: values[2],
+            opponent_runes: values[3],
+        }
+    }
+}
+
+/// One `name = value` line per weight.
+impl Display for Weights {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, value) in Weights::NAMES.iter().zip(self.to_vec()) {
+            writeln!(f, "{name} = {value}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Weights {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut values = Weights::default().to_vec();
+        for line in s
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        {
+            let (name, value) = line
+                .split_once('=')
+                .ok_or(format!("Expected `name = value`, got `{line}`"))?;
+            let idx = Weights::NAMES
+                .iter()
+                .position(|&n| n == name.trim())
+                .ok_or(format!("Unknown weight `{}`", name.trim()))?;
+            values[idx] = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid value for `{}`", name.trim()))?;
+        }
+        Ok(Weights::from_slice(&values))
+    }
+}
+
 #[derive(Default, Clone)]
-struct Eval {}
+pub struct Eval {
+    pub weights: Weights,
+}
+
+impl Eval {
+    pub fn new(weights: Weights) -> Self {
+        Eval { weights }
+    }
+}
+
 impl minimax::Evaluator for Eval {
     type G = Game;
 
@@ -147,8 +581,15 @@ impl minimax::Evaluator for Eval {
             (0..s.board.size).flat_map(|i| (0..s.board.size).map(move |j| Position(i, j)));
         let mut w_opp = 0;
         let mut k_opp = 0;
+        let mut w_runes = 0;
+        let mut k_runes = 0;
         let next_player_symbol = s.next_player_symbol();
         for pos in positions {
+            match s.board.field_at(&pos) {
+                Field::Wealth => w_runes += 1,
+                Field::Knowledge => k_runes += 1,
+                _ => (),
+            }
             let (empty_count, birth_count, gift_count, wealth_count, knowledge_count) =
                 s.board.count_around(&pos);
 
@@ -160,10 +601,17 @@ impl minimax::Evaluator for Eval {
                 }
             }
         }
-        (if next_player_symbol == Field::Wealth {
-            w_opp * 3 - k_opp
+        let (own_opp, other_opp, own_runes, other_runes) = if next_player_symbol == Field::Wealth {
+            (w_opp, k_opp, w_runes, k_runes)
         } else {
-            k_opp * 3 - w_opp
-        }) as minimax::Evaluation
+            (k_opp, w_opp, k_runes, w_runes)
+        };
+        let w = &self.weights;
+        let value = w.own_joy_squares as i32 * own_opp
+            + w.opponent_joy_squares as i32 * other_opp
+            + w.own_runes as i32 * own_runes
+            + w.opponent_runes as i32 * other_runes;
+        // Stay clear of the values the search uses for won and lost games.
+        value.clamp(-EVAL_LIMIT, EVAL_LIMIT) as minimax::Evaluation
     }
 }
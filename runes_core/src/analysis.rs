@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use minimax::{Evaluation, Strategy, BEST_EVAL};
+
+use crate::{
+    ai_player::{Eval, FORCED_JOY_DEPTH},
+    game::{Game, Move},
+    mcts::{Mcts, MctsOptions},
+    search::AlphaBeta,
+    solver,
+};
+
+/// Which search ranks the moves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Engine {
+    /// Alpha-beta to a fixed depth. Scores are evaluations.
+    Negamax { depth: u8 },
+    /// Tree search for a fixed time. Scores are win rates from -1000 (lost) to 1000 (won).
+    Mcts { time: Duration },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    pub engine: Engine,
+    /// How many moves to return at most.
+    pub candidates: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            engine: Engine::Negamax { depth: 2 },
+            candidates: 3,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CandidateMove {
+    pub mv: Move,
+    /// Higher is better for the side to move.
+    pub score: Evaluation,
+    /// Expected continuation, starting with `mv`.
+    pub pv: Vec<Move>,
+}
+
+/// Ranks the moves of the side to move, best first.
+///
+/// A forced Joy found by the solver always comes first, with a score just below `BEST_EVAL`.
+pub fn analyze(game: &Game, limits: &Limits) -> Vec<CandidateMove> {
+    if game.game_over || limits.candidates == 0 {
+        return Vec::new();
+    }
+    let mut candidates: Vec<CandidateMove> = match limits.engine {
+        Engine::Negamax { depth } => AlphaBeta::new(Eval::default(), depth)
+            .score_lines(game, game.generate_moves())
+            .into_iter()
+            .map(|(score, pv)| CandidateMove {
+                mv: pv[0],
+                score,
+                pv,
+            })
+            .collect(),
+        Engine::Mcts { time } => {
+            let mut mcts = Mcts::new(MctsOptions {
+                timeout: time,
+                ..MctsOptions::default()
+            });
+            mcts.choose_move(game);
+            let pv = mcts.principal_variation();
+            mcts.root_stats()
+                .iter()
+                .map(|stats| CandidateMove {
+                    mv: stats.mv,
+                    score: stats.score(),
+                    pv: if pv.first() == Some(&stats.mv) {
+                        pv.clone()
+                    } else {
+                        vec![stats.mv]
+                    },
+                })
+                .collect()
+        }
+    };
+    if let Some(line) = solver::forced_joy(game, FORCED_JOY_DEPTH) {
+        candidates.retain(|c| c.mv != line[0]);
+        candidates.insert(
+            0,
+            CandidateMove {
+                mv: line[0],
+                score: BEST_EVAL - line.len() as Evaluation,
+                pv: line,
+            },
+        );
+    }
+    candidates.truncate(limits.candidates);
+    candidates
+}
@@ -0,0 +1,250 @@
+use std::{
+    fmt::Write,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{game::Game, player_spec::PlayerSpec, session::Session};
+
+/// Many games between the same two computer players, to measure things like the advantage
+/// of moving first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Batch {
+    /// The players of Wealth and Knowledge.
+    pub players: [PlayerSpec; 2],
+    pub board_size: usize,
+    /// The side that moves first.
+    pub first: u8,
+    pub games: usize,
+    /// Game `i` seeds its players with `seat_seeds(seed + i)`, so every game can be played
+    /// again alone.
+    pub seed: u64,
+    pub threads: usize,
+}
+
+impl Batch {
+    pub fn new(players: [PlayerSpec; 2], board_size: usize, games: usize) -> Self {
+        Batch {
+            players,
+            board_size,
+            first: 0,
+            games,
+            seed: 0,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+}
+
+/// Why a game ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EndReason {
+    Joy,
+    FullBoard,
+    /// A player failed to make a move.
+    Forfeit,
+}
+
+impl EndReason {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EndReason::Joy => "joy",
+            EndReason::FullBoard => "full board",
+            EndReason::Forfeit => "forfeit",
+        }
+    }
+}
+
+/// The outcome of one game of a batch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GameStats {
+    /// Counts from 0 in the order the games were scheduled.
+    pub index: usize,
+    pub seed: u64,
+    /// 0 for Wealth, 1 for Knowledge.
+    pub winner: Option<u8>,
+    pub plies: usize,
+    pub end: EndReason,
+    pub duration: Duration,
+}
+
+/// The seeds of the players of Wealth and Knowledge in the game seeded with `seed`. Unlike
+/// `seed` and `seed + 1` they are unrelated to those of the games with the next seeds.
+pub fn seat_seeds(seed: u64) -> [u64; 2] {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    // Openings and setups draw from the first stream of the same seed.
+    rng.set_stream(1);
+    [rng.gen(), rng.gen()]
+}
+
+fn play(batch: &Batch, index: usize) -> Result<GameStats, String> {
+    let seed = batch.seed.wrapping_add(index as u64);
+    let [wealth, knowledge] = seat_seeds(seed);
+    let first = batch.players[0].create(Some(wealth))?;
+    let second = batch.players[1].create(Some(knowledge))?;
+    let board = Game::new(batch.board_size).board;
+    let game = Game::from_board(board, batch.first);
+    let mut session = Session::with_game(first, second, game);
+    let started = Instant::now();
+    session.start_loop(|_| {});
+    let end = if session.forfeit.is_some() {
+        EndReason::Forfeit
+    } else if session.game.winner().is_some() {
+        EndReason::Joy
+    } else {
+        EndReason::FullBoard
+    };
+    Ok(GameStats {
+        index,
+        seed,
+        winner: session.winner_index(),
+        plies: session.moves.len(),
+        end,
+        duration: started.elapsed(),
+    })
+}
+
+/// Plays the games of `batch` on `batch.threads` threads and calls `report` after each.
+/// The results are in the order of the games; the first error stops the batch.
+pub fn simulate(
+    batch: &Batch,
+    report: impl Fn(&GameStats) + Sync,
+) -> Result<Vec<GameStats>, String> {
+    run_jobs(
+        batch.games,
+        batch.threads,
+        |i| play(batch, i),
+        |done| {
+            report(done.last().unwrap());
+            true
+        },
+    )
+}
+
+/// Runs `job(i)` for every `i` below `count` on `threads` threads, handing out the jobs in
+/// order. After each one `report` gets all results so far, the newest last, and stops the
+/// jobs not yet started by returning `false`. The results are in the order of the jobs;
+/// the first error stops the run.
+pub fn run_jobs<T: Send>(
+    count: usize,
+    threads: usize,
+    job: impl Fn(usize) -> Result<T, String> + Sync,
+    report: impl Fn(&[T]) -> bool + Sync,
+) -> Result<Vec<T>, String> {
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    // The results as they come in, with the index of their job.
+    let done: Mutex<(Vec<T>, Vec<usize>)> = Mutex::new((Vec::new(), Vec::new()));
+    let error: Mutex<Option<String>> = Mutex::new(None);
+    thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, count.max(1)) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= count || stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                match job(i) {
+                    Ok(result) => {
+                        let mut done = done.lock().unwrap();
+                        done.0.push(result);
+                        done.1.push(i);
+                        if !report(&done.0) {
+                            stop.store(true, Ordering::Relaxed);
+                        }
+                    }
+                    Err(e) => {
+                        *error.lock().unwrap() = Some(e);
+                        stop.store(true, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+    });
+    if let Some(e) = error.into_inner().unwrap() {
+        return Err(e);
+    }
+    let (results, indices) = done.into_inner().unwrap();
+    let mut results: Vec<(usize, T)> = indices.into_iter().zip(results).collect();
+    results.sort_by_key(|(i, _)| *i);
+    Ok(results.into_iter().map(|(_, result)| result).collect())
+}
+
+/// One line per game under a header line.
+pub fn csv(stats: &[GameStats]) -> String {
+    let mut csv = String::from("game,seed,winner,plies,end,duration_ms\n");
+    for s in stats {
+        let winner = match s.winner {
+            Some(0) => "wealth",
+            Some(_) => "knowledge",
+            None => "draw",
+        };
+        writeln!(
+            csv,
+            "{},{},{winner},{},{},{}",
+            s.index + 1,
+            s.seed,
+            s.plies,
+            s.end.name(),
+            s.duration.as_millis()
+        )
+        .unwrap();
+    }
+    csv
+}
+
+/// Wins of each side, how the games ended and how long they took.
+pub fn summary(batch: &Batch, stats: &[GameStats]) -> String {
+    let games = stats.len().max(1) as f64;
+    let share = |n: usize| format!("{n:>6} {:>5.1}%", n as f64 * 100.0 / games);
+    let count = |f: &dyn Fn(&GameStats) -> bool| stats.iter().filter(|s| f(s)).count();
+    let first = ["Wealth", "Knowledge"][batch.first as usize];
+    let plies: usize = stats.iter().map(|s| s.plies).sum();
+    let time: Duration = stats.iter().map(|s| s.duration).sum();
+    let rows = [
+        (
+            format!("Wealth wins ({})", batch.players[0]),
+            share(count(&|s| s.winner == Some(0))),
+        ),
+        (
+            format!("Knowledge wins ({})", batch.players[1]),
+            share(count(&|s| s.winner == Some(1))),
+        ),
+        ("Draws".to_string(), share(count(&|s| s.winner.is_none()))),
+        (
+            "Ended by Joy".to_string(),
+            share(count(&|s| s.end == EndReason::Joy)),
+        ),
+        (
+            "Ended on a full board".to_string(),
+            share(count(&|s| s.end == EndReason::FullBoard)),
+        ),
+        (
+            "Ended by forfeit".to_string(),
+            share(count(&|s| s.end == EndReason::Forfeit)),
+        ),
+        (
+            "Plies per game".to_string(),
+            format!("{:>6.1}", plies as f64 / games),
+        ),
+        (
+            "Time per game".to_string(),
+            format!("{:>6.0}ms", time.as_millis() as f64 / games),
+        ),
+    ];
+    let mut text = format!(
+        "{} games on {size}x{size}, {first} first\n",
+        stats.len(),
+        size = batch.board_size
+    );
+    let width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    for (label, value) in rows {
+        writeln!(text, "{label:<width$} {value}").unwrap();
+    }
+    text
+}
@@ -1,13 +1,17 @@
-use std::fmt::{Debug, Display};
-use colorize::AnsiColor;
+use std::{
+    fmt::{Debug, Display},
+    str::FromStr,
+};
 
-#[derive(Clone)]
+use crate::render::BoardRenderer;
+
+#[derive(Clone, PartialEq, Eq)]
 pub struct Board {
     fields: Vec<Field>,
     pub size: usize,
 }
 
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
 pub struct Position(pub usize, pub usize);
 impl Position {
     pub fn near(&self, other: &Position) -> bool {
@@ -15,6 +19,66 @@ impl Position {
     }
 }
 
+/// The letters of column `col`: `a` to `z`, then `aa`, `ab` and so on.
+pub fn column_name(col: usize) -> String {
+    let mut name = Vec::new();
+    let mut n = col + 1;
+    while n > 0 {
+        n -= 1;
+        name.push(b'a' + (n % 26) as u8);
+        n /= 26;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap()
+}
+
+/// The column named `letters`, in either case.
+pub fn parse_column(letters: &str) -> Option<usize> {
+    if letters.is_empty() || !letters.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    letters
+        .to_ascii_lowercase()
+        .bytes()
+        .try_fold(0usize, |n, b| {
+            n.checked_mul(26)?.checked_add((b - b'a') as usize + 1)
+        })
+        .map(|n| n - 1)
+}
+
+/// The column letter and the row number from 1 at the top, e.g. `g7`.
+impl Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", column_name(self.1), self.0 + 1)
+    }
+}
+
+/// Reads what `Display` writes, and the 1-based `row,col` of older records.
+impl FromStr for Position {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid position `{s}`");
+        let number = |n: &str| match n.trim().parse::<usize>() {
+            Ok(n) if n > 0 => Ok(n - 1),
+            _ => Err(invalid()),
+        };
+        if let Some((row, col)) = s.split_once(',') {
+            return Ok(Position(number(row)?, number(col)?));
+        }
+        let s = s.trim();
+        let digits = s
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .ok_or_else(invalid)?;
+        let (letters, row) = s.split_at(digits);
+        let col = parse_column(letters).ok_or_else(invalid)?;
+        if !row.chars().all(|c| c.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        Ok(Position(number(row)?, col))
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash)]
 #[repr(u8)]
 pub enum Field {
@@ -26,6 +90,44 @@ pub enum Field {
     Joy = 5,
 }
 
+impl Field {
+    /// The English name, without the rune or colors.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Field::Empty => "Empty",
+            Field::Birth => "Birth",
+            Field::Gift => "Gift",
+            Field::Wealth => "Wealth",
+            Field::Knowledge => "Knowledge",
+            Field::Joy => "Joy",
+        }
+    }
+
+    /// Single letter without colors, as `Debug` prints it.
+    pub fn letter(&self) -> char {
+        match self {
+            Field::Empty => '.',
+            Field::Birth => 'B',
+            Field::Gift => 'X',
+            Field::Wealth => 'W',
+            Field::Knowledge => 'K',
+            Field::Joy => 'J',
+        }
+    }
+
+    pub fn from_letter(letter: char) -> Option<Field> {
+        match letter.to_ascii_uppercase() {
+            '.' => Some(Field::Empty),
+            'B' => Some(Field::Birth),
+            'X' => Some(Field::Gift),
+            'W' => Some(Field::Wealth),
+            'K' => Some(Field::Knowledge),
+            'J' => Some(Field::Joy),
+            _ => None,
+        }
+    }
+}
+
 impl Board {
     pub fn new(size: usize) -> Self {
         Board {
@@ -93,44 +195,44 @@ impl Board {
     pub fn field_at(&self, pos: &Position) -> Field {
         self.fields[pos.0 * self.size + pos.1]
     }
+
+    /// One letter per field, rows separated by `/`, e.g. `.../.B./...`.
+    pub fn notation(&self) -> String {
+        let rows: Vec<String> = self
+            .fields
+            .chunks(self.size)
+            .map(|row| row.iter().map(Field::letter).collect())
+            .collect();
+        rows.join("/")
+    }
 }
 
-impl Display for Board {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        //Header
-        write!(f, "    ")?;
-        for i in 0..self.size {
-            write!(
-                f,
-                "{}",
-                if i % 2 == 0 {
-                    format!("{:<6}", i + 1).bold().cyan()
-                } else {
-                    String::new()
-                }
-            )?;
-        }
-        writeln!(f)?;
-        //Board
-        for i in 0..self.size {
-            write!(
-                f,
-                "{} [ ",
-                if i % 2 == 0 {
-                    format!("{:2}", i + 1).bold().cyan()
-                } else {
-                    "  ".to_string()
-                }
-            )?;
-            for j in 0..self.size {
-                write!(f, "{:?}", self.fields[i * self.size + j])?;
-                if j < self.size - 1 {
-                    write!(f, ", ")?;
-                }
+/// Reads `Board::notation`.
+impl FromStr for Board {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows: Vec<&str> = s.trim().split('/').collect();
+        let size = rows.len();
+        let mut board = Board::new(size);
+        for (i, row) in rows.iter().enumerate() {
+            if row.chars().count() != size {
+                return Err(format!("Row {} of `{s}` doesn't have {size} fields", i + 1));
+            }
+            for (j, letter) in row.chars().enumerate() {
+                let field =
+                    Field::from_letter(letter).ok_or(format!("Unknown field `{letter}`"))?;
+                board.change(Position(i, j), field);
             }
-            writeln!(f, "]")?;
         }
-        Ok(())
+        Ok(board)
+    }
+}
+
+/// The board in plain letters with row and column numbers, see `BoardRenderer` for others.
+impl Display for Board {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", BoardRenderer::default().render(self))
     }
 }
 
@@ -140,31 +242,23 @@ impl Display for Field {
             f,
             "{}",
             match self {
-                Field::Empty => "Empty".to_string(),
-                Field::Birth => "Berkana ᛒ".to_string(),
-                Field::Gift => "Gebo X".b_green(),
-                Field::Wealth => "Fehu ᚠ".yellow(),
-                Field::Knowledge => "Kano <".blue(),
-                Field::Joy => "Wunjo ᚹ".red(),
+                Field::Empty => "Empty",
+                Field::Birth => "Berkana ᛒ",
+                Field::Gift => "Gebo X",
+                Field::Wealth => "Fehu ᚠ",
+                Field::Knowledge => "Kano <",
+                Field::Joy => "Wunjo ᚹ",
             }
         )
     }
 }
 
+/// The letter of the field, a space for an empty one.
 impl Debug for Field {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        //write!(f, "{}", *self as u8)
-        write!(
-            f,
-            "{}",
-            match self {
-                Field::Empty => " ".to_string(),
-                Field::Birth => "B".to_string(),
-                Field::Gift => "X".green(),
-                Field::Wealth => "W".yellow(),
-                Field::Knowledge => "K".blue(),
-                Field::Joy => "J".b_redb(),
-            }
-        )
+        match self {
+            Field::Empty => write!(f, " "),
+            _ => write!(f, "{}", self.letter()),
+        }
     }
 }
@@ -0,0 +1,164 @@
+use std::{
+    cell::RefCell,
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    board::Field,
+    game::{Game, Move, Player},
+};
+
+/// How long an engine may take to start and answer `runes`.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Time an engine gets on top of its movetime before it loses on time.
+const MOVE_MARGIN: Duration = Duration::from_secs(1);
+
+/// A player whose moves come from an engine executable speaking the protocol of
+/// `runes_engine` (see its PROTOCOL.md) over stdin and stdout.
+///
+/// An engine that exits, answers too late or plays an illegal move forfeits the game:
+/// `try_make_move` returns the reason, and the engine isn't asked again.
+pub struct ExternalPlayer {
+    pub symbol: Field,
+    pub movetime: Duration,
+    name: String,
+    engine: RefCell<Option<Engine>>,
+}
+
+struct Engine {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl ExternalPlayer {
+    /// Starts `program` with `args` and waits for its handshake.
+    pub fn new(program: &str, args: &[&str], movetime: Duration) -> Result<Self, String> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Can't start {program}: {e}"))?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut engine = Engine {
+            child,
+            stdin,
+            lines,
+        };
+
+        let mut name = program.to_string();
+        engine.send("runes")?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        loop {
+            let line = engine.receive(deadline)?;
+            if let Some(id) = line.strip_prefix("id name ") {
+                name = id.to_string();
+            } else if line == "runesok" {
+                break;
+            }
+        }
+        Ok(ExternalPlayer {
+            symbol: Field::Empty,
+            movetime,
+            name,
+            engine: RefCell::new(Some(engine)),
+        })
+    }
+
+    /// Passes an option on to the engine, see `setoption` in the protocol.
+    pub fn set_option(&self, name: &str, value: &str) -> Result<(), String> {
+        match self.engine.borrow_mut().as_mut() {
+            Some(engine) => engine.send(&format!("setoption name {name} value {value}")),
+            None => Err("The engine has stopped".to_string()),
+        }
+    }
+
+    fn ask(engine: &mut Engine, game: &Game, movetime: Duration) -> Result<Move, String> {
+        let side = game.next_player_symbol().letter();
+        engine.send(&format!("position {} {side}", game.board.notation()))?;
+        engine.send(&format!("go movetime {}", movetime.as_millis()))?;
+        let deadline = Instant::now() + movetime + MOVE_MARGIN;
+        loop {
+            let line = engine.receive(deadline)?;
+            if let Some(answer) = line.strip_prefix("bestmove ") {
+                let m: Move = answer.parse()?;
+                if !game.is_valid_move(&m) {
+                    return Err(format!("Illegal move {m}"));
+                }
+                return Ok(m);
+            }
+        }
+    }
+}
+
+impl Engine {
+    fn send(&mut self, line: &str) -> Result<(), String> {
+        writeln!(self.stdin, "{line}")
+            .and_then(|_| self.stdin.flush())
+            .map_err(|_| "The engine has stopped".to_string())
+    }
+
+    fn receive(&self, deadline: Instant) -> Result<String, String> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        self.lines.recv_timeout(timeout).map_err(|e| match e {
+            RecvTimeoutError::Timeout => "The engine ran out of time".to_string(),
+            RecvTimeoutError::Disconnected => "The engine has stopped".to_string(),
+        })
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Player for ExternalPlayer {
+    fn set_symbol(&mut self, symbol: Field) {
+        self.symbol = symbol;
+    }
+
+    /// Panics if the engine fails; `Session` uses `try_make_move` and scores a forfeit instead.
+    fn make_move(&self, game: Game) -> Move {
+        self.try_make_move(game)
+            .unwrap_or_else(|e| panic!("{}: {e}", self.name))
+    }
+
+    fn try_make_move(&self, game: Game) -> Result<Move, String> {
+        let mut engine = self.engine.borrow_mut();
+        let result = match engine.as_mut() {
+            Some(running) => ExternalPlayer::ask(running, &game, self.movetime),
+            None => Err("The engine has stopped".to_string()),
+        };
+        if result.is_err() {
+            // Dropping the engine shuts it down.
+            *engine = None;
+        }
+        result
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+}
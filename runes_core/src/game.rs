@@ -1,48 +1,111 @@
-use std::ops::{DerefMut, Deref};
+use std::{
+    fmt::Display,
+    str::FromStr,
+    sync::{atomic::AtomicBool, Arc},
+};
 
-use crate::board::{Board, Field, Position};
+use crate::{
+    board::{Board, Field, Position},
+    search::SearchInfo,
+};
 
 pub const PLAYER_SYMBOLS: [Field; 2] = [Field::Wealth, Field::Knowledge];
 
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq)]
 pub struct Game {
     pub board: Board,
     pub game_over: bool,
     pub next_player: u8,
+    /// After the first move the other player may take over that move's side with
+    /// `Move::SWAP`, instead of moving.
+    pub pie_rule: bool,
     last_move: Option<Move>,
+    /// The moves applied so far, for `undo`.
+    history: Vec<Move>,
+    /// Who was to move before the first move in `history`.
+    first_player: u8,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct Move {
     pub position: Position,
     pub symbol: Field,
 }
 
 impl Move {
+    /// Taking the opponent's side under the pie rule. It places nothing and the side to
+    /// move stays the same, only the players change places.
+    pub const SWAP: Move = Move {
+        position: Position(0, 0),
+        symbol: Field::Empty,
+    };
+
     pub fn new(position: Position, symbol: Field) -> Self {
         Self { position, symbol }
     }
-}
-
 
-impl Deref for Move {
-    type Target = Move;
+    pub fn is_swap(&self) -> bool {
+        self.symbol == Field::Empty
+    }
+}
 
-    fn deref(&self) -> &Self::Target {
-        self
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_swap() {
+            return write!(f, "swap");
+        }
+        write!(f, "{}={}", self.position, self.symbol.letter())
     }
 }
-impl DerefMut for Move {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        self
+
+/// Reads what `Display` writes, e.g. `g7=X` or `swap`. The `=` may be left out or be a
+/// space, and the position may be the `7,6X` of older records.
+impl FromStr for Move {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("swap") {
+            return Ok(Move::SWAP);
+        }
+        let invalid = || format!("Invalid move `{s}`");
+        let symbol = s
+            .chars()
+            .last()
+            .and_then(Field::from_letter)
+            .filter(|&f| f != Field::Empty)
+            .ok_or_else(invalid)?;
+        let position = s[..s.len() - 1].trim_end();
+        let position = position.strip_suffix('=').unwrap_or(position);
+        let position = position.parse().map_err(|_| invalid())?;
+        Ok(Move::new(position, symbol))
     }
 }
 
 pub trait Player {
     fn set_symbol(&mut self, symbol: Field);
     fn make_move(&self, board: Game) -> Move;
+    /// Like `make_move`, for players that can fail, e.g. an engine that crashed.
+    /// An error forfeits the game.
+    fn try_make_move(&self, game: Game) -> Result<Move, String> {
+        Ok(self.make_move(game))
+    }
     fn name(&self) -> String;
+    /// Called with the position after this player's move, while the opponent thinks.
+    /// Players that can use the time keep searching until their next `make_move`.
+    fn ponder(&self, _game: Game) {}
+    /// Ends pondering right away, e.g. when the game is abandoned.
+    fn stop_pondering(&self) {}
+    /// A flag that makes a running `make_move` return its best move so far when set,
+    /// for players whose search can be cut short.
+    fn stop_flag(&self) -> Option<Arc<AtomicBool>> {
+        None
+    }
+    /// What the player's search found for its last move, if it searched at all.
+    fn search_info(&self) -> Option<SearchInfo> {
+        None
+    }
 }
 
 // impl PartialEq for &'a dyn Player {
@@ -59,7 +122,28 @@ impl Game {
             board,
             game_over: false,
             next_player: 0,
+            pie_rule: false,
             last_move: None,
+            history: Vec::new(),
+            first_player: 0,
+        }
+    }
+
+    /// A game continuing from `board` with `next_player` to move. A board with a Joy on it
+    /// is over and won by the owner of the rune next to the Joy.
+    pub fn from_board(board: Board, next_player: u8) -> Self {
+        let size = board.size;
+        let joy = (0..size)
+            .flat_map(|i| (0..size).map(move |j| Position(i, j)))
+            .find(|p| board.field_at(p) == Field::Joy);
+        Self {
+            game_over: joy.is_some() || board.is_full(),
+            board,
+            next_player,
+            pie_rule: false,
+            last_move: joy.map(|p| Move::new(p, Field::Joy)),
+            history: Vec::new(),
+            first_player: next_player,
         }
     }
 
@@ -67,6 +151,9 @@ impl Game {
         self.board.reset();
         self.game_over = false;
         self.next_player = 0;
+        self.last_move = None;
+        self.history.clear();
+        self.first_player = 0;
     }
 
     pub fn winner(&self) -> Option<u8> {
@@ -95,9 +182,14 @@ impl Game {
         if !self.is_valid_move(&move_to_apply) {
             return Err("Invalid move".to_string());
         }
+        if move_to_apply.is_swap() {
+            self.history.push(move_to_apply);
+            return Ok(Field::Empty);
+        }
         self.board
             .change(move_to_apply.position, move_to_apply.symbol);
         self.last_move = Some(move_to_apply);
+        self.history.push(move_to_apply);
         if move_to_apply.symbol == Field::Joy || self.board.is_full() {
             self.game_over = true;
         }
@@ -105,11 +197,128 @@ impl Game {
         Ok(move_to_apply.symbol)
     }
 
+    /// Takes back the last move applied to this game and returns it.
+    pub fn undo(&mut self) -> Option<Move> {
+        let m = self.history.pop()?;
+        if m.is_swap() {
+            return Some(m);
+        }
+        self.board.change(m.position, Field::Empty);
+        self.last_move = self.history.iter().rev().find(|m| !m.is_swap()).copied();
+        self.game_over = false;
+        self.next_player = 1 - self.next_player;
+        Some(m)
+    }
+
+    /// May the side to move answer the first move with `Move::SWAP`?
+    pub fn can_swap(&self) -> bool {
+        self.pie_rule && !self.game_over && self.history.len() == 1
+    }
+
+    /// The moves applied to this game, oldest first.
+    pub fn history(&self) -> &[Move] {
+        &self.history
+    }
+
+    /// The last rune placed, the Joy of a finished game set up with `from_board`.
+    pub fn last_move(&self) -> Option<Move> {
+        self.last_move
+    }
+
+    /// Checks that the game could have come about by legal play: every rune has the
+    /// neighbours it needs, the side to move matches the number of moves and the game is over
+    /// exactly when there is a Joy or the board is full.
+    pub fn validate(&self) -> Result<(), String> {
+        let size = self.board.size;
+        let mut joys = Vec::new();
+        for pos in (0..size).flat_map(|i| (0..size).map(move |j| Position(i, j))) {
+            let (empty_count, birth_count, gift_count, wealth_count, knowledge_count) =
+                self.board.count_around(&pos);
+            let runes = birth_count + gift_count + wealth_count + knowledge_count;
+            let field = self.board.field_at(&pos);
+            let possible = match field {
+                Field::Empty => true,
+                // A later Birth can't be placed next to an earlier one.
+                Field::Birth => birth_count == 0,
+                Field::Gift => runes > 0,
+                Field::Wealth | Field::Knowledge => birth_count > 0 && gift_count > 0,
+                Field::Joy => {
+                    joys.push(pos);
+                    birth_count == 1
+                        && gift_count == 1
+                        && empty_count == 5
+                        && wealth_count + knowledge_count == 1
+                }
+            };
+            if !possible {
+                return Err(format!(
+                    "{} at {pos} can't have been placed",
+                    field.letter()
+                ));
+            }
+        }
+        if joys.len() > 1 {
+            return Err("More than one Joy on the board".to_string());
+        }
+        let placed: Vec<&Move> = self.history.iter().filter(|m| !m.is_swap()).collect();
+        for m in &placed {
+            if self.board.field_at(&m.position) != m.symbol {
+                return Err(format!("Move {m} is missing from the board"));
+            }
+        }
+        if placed
+            .last()
+            .is_some_and(|&m| Some(m) != self.last_move.as_ref())
+        {
+            return Err("The last move isn't the last one applied".to_string());
+        }
+        if self.next_player as usize != (self.first_player as usize + placed.len()) % 2 {
+            return Err(format!(
+                "Player {} to move after {} moves",
+                self.next_player,
+                placed.len()
+            ));
+        }
+        let swaps = self.history.len() - placed.len();
+        let swapped_first = self.history.get(1).is_some_and(Move::is_swap);
+        if swaps > 0 && !(self.pie_rule && swaps == 1 && swapped_first) {
+            return Err("A swap that the pie rule doesn't allow".to_string());
+        }
+        if self.game_over != (!joys.is_empty() || self.board.is_full()) {
+            return Err(format!(
+                "The game is {}over, but the board says otherwise",
+                if self.game_over { "" } else { "not " }
+            ));
+        }
+        let owner = joys.first().map(|pos| {
+            let (_, _, _, wealth_count, _) = self.board.count_around(pos);
+            if wealth_count == 1 {
+                0
+            } else {
+                1
+            }
+        });
+        if self.winner() != owner {
+            return Err(format!(
+                "The winner is {:?}, but the Joy belongs to {owner:?}",
+                self.winner()
+            ));
+        }
+        Ok(())
+    }
+
     pub fn next_player_symbol(&self) -> Field {
         PLAYER_SYMBOLS[self.next_player as usize]
     }
 
+    pub fn opponent_symbol(&self) -> Field {
+        PLAYER_SYMBOLS[1 - self.next_player as usize]
+    }
+
     pub fn is_valid_move(&self, move_to_check: &Move) -> bool {
+        if move_to_check.is_swap() {
+            return self.can_swap();
+        }
         let pos = &move_to_check.position;
         //boundary check
         pos.0 < self.board.size && pos.1 < self.board.size
@@ -123,7 +332,7 @@ impl Game {
         if !self.board.is_empty(position) {
             return Vec::new();
         }
-        let (empty_count, birth_count, gift_count, wealth_count, knowledge_count) =
+        let (_, birth_count, gift_count, wealth_count, knowledge_count) =
             self.board.count_around(position);
 
         let mut valid = Vec::new();
@@ -136,17 +345,33 @@ impl Game {
         if birth_count > 0 && gift_count > 0 {
             valid.push(player_symbol);
         }
-        if birth_count == 1
-            && gift_count == 1
-            && empty_count == 5
-            && (knowledge_count == 1 && player_symbol == Field::Knowledge
-                || wealth_count == 1 && player_symbol == Field::Wealth)
-        {
+        if self.is_joy_square(position, player_symbol) {
             valid.push(Field::Joy);
         }
         valid
     }
 
+    /// A Joy square of `symbol`'s player: an empty field where that player could place a Joy.
+    pub fn is_joy_square(&self, position: &Position, symbol: Field) -> bool {
+        if !self.board.is_empty(position) {
+            return false;
+        }
+        let (empty_count, birth_count, gift_count, wealth_count, knowledge_count) =
+            self.board.count_around(position);
+        birth_count == 1
+            && gift_count == 1
+            && empty_count == 5
+            && (knowledge_count == 1 && symbol == Field::Knowledge
+                || wealth_count == 1 && symbol == Field::Wealth)
+    }
+
+    pub fn joy_squares(&self, symbol: Field) -> Vec<Position> {
+        (0..self.board.size)
+            .flat_map(|i| (0..self.board.size).map(move |j| Position(i, j)))
+            .filter(|pos| self.is_joy_square(pos, symbol))
+            .collect()
+    }
+
     pub fn best_symbol_at(&self, position: &Position) -> Field {
         match self.valid_symbols_at(position).iter().max() {
             Some(&f) => f,
@@ -154,7 +379,26 @@ impl Game {
         }
     }
 
-    pub fn generate_moves(&self) -> Vec<Move> {
+    /// Every move `apply_move` accepts, field by field, then the swap if it's allowed.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        if self.game_over {
+            return Vec::new();
+        }
+        let size = self.board.size;
+        let swap = self.can_swap().then_some(Move::SWAP);
+        (0..size)
+            .flat_map(|i| (0..size).map(move |j| Position(i, j)))
+            .flat_map(|pos| {
+                self.valid_symbols_at(&pos)
+                    .into_iter()
+                    .map(move |symbol| Move::new(pos, symbol))
+            })
+            .chain(swap)
+            .collect()
+    }
+
+    /// One move for every empty field, with the best symbol there.
+    pub fn candidate_moves(&self) -> Vec<Move> {
         let mut moves = Vec::new();
         for i in 0..self.board.size {
             for j in 0..self.board.size {
@@ -164,6 +408,14 @@ impl Game {
                 }
             }
         }
+        moves
+    }
+
+    /// Like `candidate_moves`, but only the winning moves if there are any, or only the moves
+    /// next to the opponent's winning fields. The swap is never among them, the searches that
+    /// use these moves expect the side to move to change.
+    pub fn generate_moves(&self) -> Vec<Move> {
+        let mut moves = self.candidate_moves();
         let player_symbol = self.next_player_symbol();
         let mut my_winning = Vec::new();
         let mut opp_winning = Vec::new();
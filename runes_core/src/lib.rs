@@ -1,11 +1,52 @@
 pub mod ai_player;
+pub mod analysis;
+pub mod batch;
 pub mod board;
+pub mod external_player;
 pub mod game;
 pub mod human_player;
+pub mod mcts;
+pub mod perft;
+pub mod player_spec;
+pub mod puzzle;
+pub mod record;
+pub mod render;
+pub mod review;
+pub mod search;
+pub mod session;
+pub mod setup;
+pub mod solver;
 
 #[cfg(test)]
 mod tests {
-    use crate::board::{Board, Field, Position};
+    use std::{
+        cell::RefCell,
+        time::{Duration, Instant},
+    };
+
+    use minimax::{MCTSOptions, MonteCarloTreeSearch, Negamax, Strategy, BEST_EVAL, WORST_EVAL};
+    use rand::{seq::SliceRandom, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{
+        ai_player::{AiPlayer, AiPlayerCasual, AiPlayerMonte, AiPlayerRandom, Eval, Level},
+        analysis::{analyze, Engine, Limits},
+        batch::{csv, run_jobs, seat_seeds, simulate, summary, Batch, EndReason, GameStats},
+        board::{Board, Field, Position},
+        external_player::ExternalPlayer,
+        game::{Game, Move, Player},
+        mcts::{Mcts, MctsOptions},
+        perft::{divide, perft, REFERENCE_COUNTS},
+        player_spec::PlayerSpec,
+        puzzle::{parse_puzzles, write_puzzles, Puzzle},
+        record::{parse_start, GameRecord},
+        render::{terminal_style, BoardRenderer, RenderStyle},
+        review::{annotate, html_report, review, Judgement, Thresholds},
+        search::AlphaBeta,
+        session::Session,
+        setup::{handicap, parse_setups, scatter, write_setups, Setup},
+        solver,
+    };
 
     #[test]
     #[rustfmt::skip]
@@ -16,12 +57,32 @@ mod tests {
         b.change(Position(3, 1), Field::Gift);
         b.change(Position(3, 2), Field::Knowledge);
         b.change(Position(2, 3), Field::Wealth);
-//         assert_eq!(format!("{:?}", b).trim_end(), 
-// "[ ,  ,  ,  ,  ]
-// [ ,  ,  , X,  ]
-// [ ,  , B, W,  ]
-// [ , X, K,  ,  ]
-// [ ,  ,  ,  ,  ]");
+        assert_eq!(b.to_string(),
+"  a b c d e
+1 . . . . .
+2 . . . X .
+3 . . B W .
+4 . X K . .
+5 . . . . .
+");
+        assert_eq!(BoardRenderer::new(RenderStyle::Runes, false).render(&b),
+". . . . .
+. . . X .
+. . ᛒ ᚠ .
+. X < . .
+. . . . .
+");
+        let ansi = BoardRenderer::new(RenderStyle::Ansi, true);
+        assert_eq!(ansi.field(Field::Empty), ".");
+        if cfg!(feature = "colorize") {
+            assert_eq!(ansi.field(Field::Gift), "\x1b[32mX\x1b[0m");
+        } else {
+            assert_eq!(ansi.render(&b), b.to_string());
+        }
+        assert_eq!(terminal_style(true, true), RenderStyle::Runes);
+        assert_eq!(terminal_style(false, false), RenderStyle::Runes);
+        let color = if cfg!(feature = "colorize") { RenderStyle::Ansi } else { RenderStyle::Runes };
+        assert_eq!(terminal_style(false, true), color);
 
         assert_eq!(
             format!("{:?}", b.fields_around(&Position(1, 2))).trim_end(),
@@ -36,4 +97,668 @@ mod tests {
             "[ ,  , X, W,  ]"
         );
     }
+
+    #[test]
+    fn coordinates() {
+        assert_eq!(Position(6, 5).to_string(), "f7");
+        assert_eq!(Position(0, 26).to_string(), "aa1");
+        for text in ["f7", "F7", " f7 ", "7,6"] {
+            assert_eq!(text.parse::<Position>(), Ok(Position(6, 5)), "{text}");
+        }
+        for text in ["f", "7", "f0", "f7x", "7,", ""] {
+            assert!(text.parse::<Position>().is_err(), "{text}");
+        }
+        let gift = Move::new(Position(6, 5), Field::Gift);
+        assert_eq!(gift.to_string(), "f7=X");
+        for text in ["f7=X", "f7X", "f7 x", "f7 = X", "7,6X"] {
+            assert_eq!(text.parse::<Move>(), Ok(gift), "{text}");
+        }
+        for text in ["f7", "f7=", "f7=.", "=X"] {
+            assert!(text.parse::<Move>().is_err(), "{text}");
+        }
+        assert_eq!("swap".parse::<Move>(), Ok(Move::SWAP));
+    }
+
+    #[test]
+    fn forced_joy() {
+        let mut game = Game::new(7);
+        game.board.reset();
+        game.board.change(Position(2, 2), Field::Wealth);
+        game.board.change(Position(2, 3), Field::Birth);
+        assert!(solver::forced_joy(&game, 1).is_none());
+
+        // A Gift next to the Birth opens two Joy squares that share no empty neighbour.
+        let line = solver::forced_joy(&game, 2).unwrap();
+        assert_eq!(line.len(), 3);
+        assert_eq!(line[0].symbol, Field::Gift);
+
+        // For the defender an attack only counts once there is a Joy square to stop.
+        let waiting = Game::from_board(game.board.clone(), 1);
+        assert!(solver::unstoppable_joy(&waiting, 2).is_none());
+        let mut attacked = game.clone();
+        attacked.apply_move(line[0]).unwrap();
+        assert_eq!(solver::unstoppable_joy(&attacked, 1), Some(line[1..].to_vec()));
+
+        for m in line {
+            game.apply_move(m).unwrap();
+        }
+        assert_eq!(game.winner(), Some(0));
+    }
+
+    #[test]
+    fn casual_player_takes_joy() {
+        let mut game = Game::new(7);
+        game.board.change(Position(2, 2), Field::Wealth);
+        game.board.change(Position(2, 4), Field::Gift);
+        for level in [Level::Easy, Level::Medium, Level::Hard, Level::VeryHard] {
+            let player = AiPlayerCasual::new(level);
+            assert_eq!(
+                player.make_move(game.clone()),
+                Move::new(Position(2, 3), Field::Joy)
+            );
+        }
+    }
+
+    #[test]
+    fn analysis_ranks_forced_joy_first() {
+        let mut game = Game::new(7);
+        game.board.reset();
+        game.board.change(Position(2, 2), Field::Wealth);
+        game.board.change(Position(2, 3), Field::Birth);
+        for engine in [
+            Engine::Negamax { depth: 2 },
+            Engine::Mcts {
+                time: Duration::from_millis(50),
+            },
+        ] {
+            let candidates = analyze(&game, &Limits { engine, candidates: 2 });
+            assert_eq!(candidates.len(), 2);
+            assert_eq!(candidates[0].pv.len(), 3);
+            assert_eq!(candidates[0].pv.last().unwrap().symbol, Field::Joy);
+            assert!(candidates.iter().all(|c| c.pv[0] == c.mv));
+            assert!(candidates[0].score > candidates[1].score);
+        }
+    }
+
+    #[test]
+    fn ponder_then_move() {
+        let mut mcts = Mcts::new(MctsOptions {
+            threads: 2,
+            timeout: Duration::from_millis(50),
+            ..MctsOptions::default()
+        });
+        let mut game = Game::new(7);
+        let own = mcts.choose_move(&game).unwrap();
+        game.apply_move(own).unwrap();
+        mcts.ponder(&game);
+        assert!(mcts.is_pondering());
+        std::thread::sleep(Duration::from_millis(50));
+        let reply = game.generate_moves()[0];
+        game.apply_move(reply).unwrap();
+
+        let started = Instant::now();
+        let mv = mcts.choose_move(&game).unwrap();
+        assert!(started.elapsed() < Duration::from_millis(500));
+        assert!(!mcts.is_pondering());
+        assert!(game.generate_moves().contains(&mv));
+    }
+
+    #[test]
+    fn pondering_stays_within_its_memory() {
+        let budget = 64 << 10;
+        let mut mcts = Mcts::new(MctsOptions {
+            threads: 2,
+            ponder_memory: budget,
+            ..MctsOptions::default()
+        });
+        let mut game = Game::new(9);
+        game.apply_move(game.generate_moves()[0]).unwrap();
+        mcts.ponder(&game);
+        std::thread::sleep(Duration::from_millis(300));
+        mcts.stop_pondering();
+        // Each thread may overshoot by the last node it expanded.
+        assert!(mcts.tree_memory() >= budget / 2);
+        assert!(mcts.tree_memory() < budget + (8 << 10));
+    }
+
+    #[test]
+    fn seeded_players_repeat_moves() {
+        let mut game = Game::new(9);
+        for _ in 0..3 {
+            game.apply_move(game.generate_moves()[0]).unwrap();
+        }
+        for _ in 0..2 {
+            let random = AiPlayerRandom::with_seed(Level::Easy, Some(42));
+            let casual = AiPlayerCasual::with_seed(Level::Easy, Some(42));
+            let monte = AiPlayerMonte::deterministic(Level::Easy, 42);
+            assert_eq!(
+                random.make_move(game.clone()),
+                Move::new(Position(2, 2), Field::Birth)
+            );
+            assert_eq!(
+                casual.make_move(game.clone()),
+                Move::new(Position(0, 4), Field::Birth)
+            );
+            assert_eq!(
+                monte.make_move(game.clone()),
+                Move::new(Position(3, 4), Field::Gift)
+            );
+        }
+
+        // A deterministic player that already played gives the move of a fresh one.
+        let used = AiPlayerMonte::deterministic(Level::Easy, 7);
+        let mut game = Game::new(9);
+        for _ in 0..3 {
+            game.apply_move(used.make_move(game.clone())).unwrap();
+            game.apply_move(game.generate_moves()[0]).unwrap();
+        }
+        assert!(!game.game_over);
+        let fresh = AiPlayerMonte::deterministic(Level::Easy, 7);
+        assert_eq!(used.make_move(game.clone()), fresh.make_move(game));
+    }
+
+    #[test]
+    fn search_info_describes_move() {
+        let game = Game::new(7);
+        let players: [Box<dyn Player>; 4] = [
+            Box::new(AiPlayerRandom::with_seed(Level::Easy, Some(1))),
+            Box::new(AiPlayer::new(Level::Medium)),
+            Box::new(AiPlayerCasual::with_seed(Level::Medium, Some(1))),
+            Box::new(AiPlayerMonte::deterministic(Level::Easy, 1)),
+        ];
+        for player in players {
+            assert!(player.search_info().is_none());
+            let mv = player.make_move(game.clone());
+            let info = player.search_info().unwrap();
+            assert_eq!(info.pv[0], mv);
+            assert!(info.nodes > 0);
+            assert!(info.depth.is_some() != info.playouts.is_some());
+        }
+    }
+
+    /// An engine that always answers `c3=X`, legal only the first time on a 7x7 board.
+    #[cfg(unix)]
+    const STUBBORN_ENGINE: &str = r#"
+        while read line; do
+            case "$line" in
+                runes) echo "id name Stubborn"; echo runesok ;;
+                go*) echo "bestmove c3=X" ;;
+            esac
+        done"#;
+
+    #[test]
+    #[cfg(unix)]
+    fn external_engine_forfeits() {
+        let movetime = Duration::from_millis(10);
+        let stubborn = ExternalPlayer::new("sh", &["-c", STUBBORN_ENGINE], movetime).unwrap();
+        assert_eq!(stubborn.name(), "Stubborn");
+        let random = AiPlayerRandom::with_seed(Level::Easy, Some(1));
+        let mut session = Session::new(Box::new(stubborn), Box::new(random), 7);
+        session.start_loop(|_| {});
+        assert_eq!(session.game.board.field_at(&Position(2, 2)), Field::Gift);
+        let forfeit = session.forfeit.clone().unwrap();
+        assert_eq!(forfeit.player, 0);
+        assert_eq!(forfeit.reason, "Illegal move c3=X");
+        assert_eq!(session.winner_index(), Some(1));
+
+        // A player that doesn't check its moves forfeits just the same.
+        struct Stubborn;
+        impl Player for Stubborn {
+            fn set_symbol(&mut self, _symbol: Field) {}
+            fn make_move(&self, _game: Game) -> Move {
+                Move::new(Position(2, 2), Field::Gift)
+            }
+            fn name(&self) -> String {
+                "Stubborn".to_string()
+            }
+        }
+        let random = AiPlayerRandom::with_seed(Level::Easy, Some(1));
+        let mut session = Session::new(Box::new(random), Box::new(Stubborn), 7);
+        session.start_loop(|_| {});
+        let forfeit = session.forfeit.clone().unwrap();
+        assert_eq!(forfeit.player, 1);
+        assert_eq!(forfeit.reason, "Invalid move c3=X");
+
+        let silent = "read line; echo runesok; while read line; do :; done";
+        let silent = ExternalPlayer::new("sh", &["-c", silent], movetime).unwrap();
+        let reason = silent.try_make_move(Game::new(7)).unwrap_err();
+        assert_eq!(reason, "The engine ran out of time");
+        let crashed = "read line; echo runesok; read line; exit 1";
+        let crashed = ExternalPlayer::new("sh", &["-c", crashed], movetime).unwrap();
+        let reason = crashed.try_make_move(Game::new(7)).unwrap_err();
+        assert_eq!(reason, "The engine has stopped");
+    }
+
+    #[test]
+    fn perft_reference_counts() {
+        for (size, counts) in REFERENCE_COUNTS {
+            let game = Game::new(size);
+            // Bigger counts take minutes without optimizations, see `perft_deep`.
+            let quick = counts.iter().take_while(|&&count| count < 1_000_000);
+            for (depth, &count) in quick.enumerate() {
+                assert_eq!(perft(&game, depth + 1), count, "{size}x{size} depth {}", depth + 1);
+            }
+        }
+        let mut game = Game::new(7);
+        game.apply_move(Move::new(Position(2, 2), Field::Gift)).unwrap();
+        let split = divide(&game, 2);
+        assert_eq!(split.len(), game.legal_moves().len());
+        assert_eq!(split.iter().map(|(_, n)| n).sum::<u64>(), perft(&game, 2));
+    }
+
+    #[test]
+    #[ignore = "slow, run with --release -- --ignored"]
+    fn perft_deep() {
+        for (size, counts) in REFERENCE_COUNTS {
+            for (depth, &count) in counts.iter().enumerate() {
+                assert_eq!(perft(&Game::new(size), depth + 1), count);
+            }
+        }
+    }
+
+    #[test]
+    fn random_games_keep_invariants() {
+        for seed in 0..40 {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let size = [5, 7, 9, 13][seed as usize % 4];
+            let mut game = Game::new(size);
+            while !game.game_over {
+                assert_eq!(game.validate(), Ok(()), "seed {seed}");
+                let legal = game.legal_moves();
+                assert!(game.generate_moves().iter().all(|m| legal.contains(m)));
+                let m = *legal.choose(&mut rng).unwrap();
+                let before = game.clone();
+                game.apply_move(m).unwrap();
+                assert_eq!(game.validate(), Ok(()), "seed {seed} after {m}");
+                assert_eq!(game.undo(), Some(m));
+                assert!(game == before, "seed {seed}: undo {m}");
+                game.apply_move(m).unwrap();
+            }
+            assert_eq!(game.validate(), Ok(()));
+            assert!(game.legal_moves().is_empty());
+        }
+    }
+
+    #[test]
+    fn validate_finds_broken_games() {
+        let mut game = Game::new(5);
+        game.board.change(Position(1, 1), Field::Birth);
+        game.board.change(Position(0, 0), Field::Gift);
+        assert!(game.validate().is_err());
+
+        let mut game = Game::new(5);
+        game.next_player = 1;
+        assert!(game.validate().is_err());
+
+        let mut game = Game::new(5);
+        game.game_over = true;
+        assert!(game.validate().is_err());
+    }
+
+    #[test]
+    fn puzzle_has_unique_key() {
+        let text = "Joy in: 2
+Start: ....B..../........./.......B./......X../....B..../........./........./....B..../......... W
+Solution: f4=W f2=X f5=J
+";
+        let puzzles = parse_puzzles(text).unwrap();
+        assert_eq!(write_puzzles(&puzzles), text);
+        let found = Puzzle::find(&puzzles[0].start, 3).unwrap();
+        assert!(found == puzzles[0]);
+        assert_eq!(solver::winning_moves(&found.start, 2), found.solution[..1]);
+
+        // The Birth between the two Wealth runes makes no Joy square, but leaves two
+        // Gifts that each make two, too far apart to stop with one move.
+        let quiet = parse_start(
+            "........./...B...../........./........./.W...W.../........./........./...B...../......... W",
+        )
+        .unwrap();
+        assert!(solver::forced_joy(&quiet, 3).is_none());
+        let d5 = Move::new(Position(4, 3), Field::Birth);
+        assert!(solver::winning_moves(&quiet, 3).contains(&d5));
+
+        // After the key move it's the defender's turn, nothing to solve there.
+        let mut game = found.start.clone();
+        game.apply_move(found.solution[0]).unwrap();
+        assert!(Puzzle::find(&game, 3).is_none());
+        assert!(parse_puzzles(&text.replace("f5=J", "e5=J")).is_err());
+
+        // Solving it: the key, the defender's reply from the solution, the Joy.
+        let puzzle = &puzzles[0];
+        let [key, reply, joy] = puzzle.solution[..] else {
+            panic!("not a Joy in 2");
+        };
+        assert!(!puzzle.accepts(&[], Move::new(Position(0, 0), Field::Birth)));
+        assert!(puzzle.accepts(&[], key));
+        assert_eq!(puzzle.reply(&[key]), Some(reply));
+        assert!(puzzle.accepts(&[key, reply], joy));
+        // Any other defence loses as well, the solver knows how.
+        let other = puzzle.position_after(&[key]).unwrap().legal_moves()[0];
+        let after = puzzle.position_after(&[key, other]).unwrap();
+        assert!(after.legal_moves().iter().any(|&m| puzzle.accepts(&[key, other], m)));
+    }
+
+    #[test]
+    fn review_finds_missed_joy() {
+        let mut game = Game::new(7);
+        game.board.reset();
+        game.board.change(Position(2, 2), Field::Wealth);
+        game.board.change(Position(2, 3), Field::Birth);
+        let limits = Limits::default();
+        let thresholds = Thresholds::for_engine(&limits.engine);
+
+        // Playing the forced line costs nothing.
+        let line = solver::forced_joy(&game, 2).unwrap();
+        let record = GameRecord::new(&game, line.clone());
+        let reviews = review(&record, &limits, &thresholds).unwrap();
+        assert_eq!(reviews.len(), line.len());
+        assert!(reviews.iter().all(|r| r.judgement.is_none() && r.missed_joy.is_none()));
+
+        // A Birth in the corner gives the win away.
+        let corner = Move::new(Position(6, 6), Field::Birth);
+        let record = GameRecord::new(&game, vec![corner]);
+        let reviews = review(&record, &limits, &thresholds).unwrap();
+        assert_eq!(reviews[0].judgement, Some(Judgement::Blunder));
+        assert_eq!(reviews[0].missed_joy.as_ref().unwrap().len(), 3);
+
+        let annotated = annotate(&record, &reviews, "negamax");
+        assert!(annotated.comments[0].starts_with("?? blunder"));
+        assert_eq!(annotated.to_string().parse::<GameRecord>().unwrap(), annotated);
+        let html = html_report(&annotated, &reviews).unwrap();
+        assert!(html.contains("class=\"board\"") && html.contains("missed Joy"));
+
+        // After a swap Knowledge is still to move, its blunder is its own.
+        game.pie_rule = true;
+        let far = Move::new(Position(0, 6), Field::Birth);
+        let record = GameRecord::new(&game, vec![corner, Move::SWAP, far]);
+        let reviews = review(&record, &limits, &thresholds).unwrap();
+        let html = html_report(&record, &reviews).unwrap();
+        assert_eq!(reviews[2].judgement, Some(Judgement::Blunder));
+        assert!(html.contains("<tr><th>Wealth</th><td>1</td>"));
+        assert!(html.contains("<tr><th>Knowledge</th><td>1</td>"));
+    }
+
+    #[test]
+    fn pie_rule_swaps_sides() {
+        let mut game = Game::new(7);
+        game.pie_rule = true;
+        assert!(!game.can_swap());
+        game.apply_move(game.generate_moves()[0]).unwrap();
+        assert!(game.legal_moves().contains(&Move::SWAP));
+        let before = game.clone();
+        game.apply_move(Move::SWAP).unwrap();
+        assert_eq!(game.next_player, 1);
+        assert_eq!(game.validate(), Ok(()));
+        assert!(!game.legal_moves().contains(&Move::SWAP));
+        assert_eq!(game.undo(), Some(Move::SWAP));
+        assert!(game == before);
+        game.pie_rule = false;
+        assert!(game.apply_move(Move::SWAP).is_err());
+
+        // The random player swaps half of the time, the players change places when it does.
+        let session = (0..)
+            .map(|seed| {
+                let mut session = Session::with_pie_rule(
+                    Box::new(AiPlayerCasual::with_seed(Level::Easy, Some(seed))),
+                    Box::new(AiPlayerRandom::with_seed(Level::Easy, Some(seed))),
+                    7,
+                );
+                session.start_loop(|_| {});
+                session
+            })
+            .find(|session| session.swapped)
+            .unwrap();
+        assert_eq!(session.moves[1], Move::SWAP);
+        assert!(session.players[0].name().starts_with("AI Dumb"));
+        let record = GameRecord::from_session(&session);
+        assert_eq!(record.header("Rules"), Some("pie"));
+        let replayed = record.to_string().parse::<GameRecord>().unwrap().replay().unwrap();
+        assert!(replayed.board == session.game.board);
+    }
+
+    #[test]
+    fn starting_positions() {
+        let game = scatter(9, 12, 7).unwrap();
+        assert!(game == scatter(9, 12, 7).unwrap());
+        assert_eq!(game.board.notation().matches('.').count(), 81 - 12);
+        assert_eq!(game.validate(), Ok(()));
+        assert!(solver::forced_joy(&game, 3).is_none());
+
+        for runes in 0..=4 {
+            let game = handicap(9, runes, 1).unwrap();
+            assert_eq!(game.next_player, 0);
+            assert_eq!(game.board.notation().matches('K').count(), runes);
+            for player in [0, 1] {
+                let turn = Game::from_board(game.board.clone(), player);
+                assert!(turn.legal_moves().iter().all(|m| m.symbol != Field::Joy));
+            }
+        }
+        assert!(handicap(9, 5, 1).is_err());
+        assert!(handicap(7, 1, 1).is_err());
+        for size in 1..5 {
+            assert!(handicap(size, 1, 0).is_err(), "{size}");
+        }
+
+        let setups = vec![
+            Setup {
+                name: "Scattered".to_string(),
+                game,
+            },
+            Setup {
+                name: "Handicap".to_string(),
+                game: handicap(11, 2, 0).unwrap(),
+            },
+        ];
+        let text = write_setups(&setups);
+        assert!(parse_setups(&text).unwrap() == setups);
+        assert!(parse_setups("Name: Won\nStart: B.../X.../W.../.... W").is_err());
+    }
+
+    #[test]
+    fn batch_is_repeatable() {
+        let players = [PlayerSpec::Random, PlayerSpec::Casual(Level::Easy)];
+        let mut batch = Batch::new(players, 7, 12);
+        batch.seed = 9;
+        batch.threads = 1;
+        let alone = simulate(&batch, |_| {}).unwrap();
+        batch.threads = 4;
+        let parallel = simulate(&batch, |_| {}).unwrap();
+        let outcome = |stats: &[GameStats]| -> Vec<_> {
+            stats
+                .iter()
+                .map(|s| (s.index, s.seed, s.winner, s.plies, s.end))
+                .collect()
+        };
+        assert_eq!(outcome(&alone), outcome(&parallel));
+        assert!(alone.iter().all(|s| s.end != EndReason::Forfeit));
+        assert_eq!(csv(&alone).lines().count(), 13);
+        assert!(summary(&batch, &alone).starts_with("12 games on 7x7, Wealth first\n"));
+
+        // No seat of a game plays with the random numbers of a seat of the next game.
+        let [wealth, knowledge] = seat_seeds(9);
+        assert_ne!(wealth, knowledge);
+        let next = seat_seeds(10);
+        assert!(!next.contains(&wealth) && !next.contains(&knowledge));
+    }
+
+    /// The AI of the first version, playing a strategy of the `minimax` crate.
+    struct Minimax<S: Strategy<Game>>(RefCell<S>);
+
+    impl<S: Strategy<Game>> Player for Minimax<S> {
+        fn set_symbol(&mut self, _symbol: Field) {}
+
+        fn make_move(&self, game: Game) -> Move {
+            self.0.borrow_mut().choose_move(&game).unwrap()
+        }
+
+        fn name(&self) -> String {
+            "minimax".to_string()
+        }
+    }
+
+    /// The share of points of `new` in `games` games against `old` on a 9x9 board, in pairs
+    /// from the same two random plies with the first player alternating. The players get the
+    /// number of the game.
+    fn score_against(
+        games: usize,
+        threads: usize,
+        new: impl Fn(usize) -> Box<dyn Player> + Sync,
+        old: impl Fn(usize) -> Box<dyn Player> + Sync,
+    ) -> f64 {
+        let points = run_jobs(
+            games,
+            threads,
+            |i| {
+                let mut rng = ChaCha8Rng::seed_from_u64(i as u64 / 2);
+                let mut game = Game::new(9);
+                for _ in 0..2 {
+                    game.apply_move(*game.generate_moves().choose(&mut rng).unwrap())?;
+                }
+                let new_first = i % 2 == 0;
+                let players = if new_first {
+                    (new(i), old(i))
+                } else {
+                    (old(i), new(i))
+                };
+                let mut session = Session::with_game(players.0, players.1, game);
+                session.start_loop(|_| {});
+                Ok(match session.winner_index() {
+                    Some(w) if (w == 0) == new_first => 2,
+                    Some(_) => 0,
+                    None => 1,
+                })
+            },
+            |_| true,
+        )
+        .unwrap();
+        points.iter().sum::<u32>() as f64 / (2 * games) as f64
+    }
+
+    /// The win rates `Level::difficulty` is calibrated to, within two standard deviations of
+    /// 200 games, and each level stronger than the one below. Takes about five minutes.
+    /// Run with `cargo test --release -p runes_core -- --ignored casual_levels --nocapture`.
+    #[test]
+    #[ignore]
+    fn casual_levels_hit_their_targets() {
+        let targets = [
+            (Level::Easy, 0.1),
+            (Level::Medium, 0.3),
+            (Level::Hard, 0.4),
+            (Level::VeryHard, 0.95),
+        ];
+        let mut below = 0.0;
+        for (level, target) in targets {
+            let score = score_against(
+                200,
+                4,
+                |i| Box::new(AiPlayerCasual::with_seed(level, Some(i as u64))),
+                |_| Box::new(AiPlayer::new(Level::Medium)),
+            );
+            println!("AiPlayerCasual {level:?}: {:.1}%", score * 100.0);
+            assert!(score > below, "{level:?} is weaker than the level below");
+            below = score;
+            // VeryHard falls short of its target, see `Level::difficulty`.
+            if level != Level::VeryHard {
+                assert!((score - target).abs() <= 0.07, "{level:?}");
+            }
+        }
+    }
+
+    /// `AlphaBeta` and `Mcts` replaced `minimax::Negamax` and `minimax::MonteCarloTreeSearch`
+    /// in the AI players, which must not be clearly weaker than before: a score below 40%
+    /// is outside the noise of this many games. Last measured 53.8% (Medium), 51.2% (Hard)
+    /// and 47.5% (MCTS Easy).
+    /// Run with `cargo test --release -p runes_core -- --ignored replaced_engines --nocapture`.
+    #[test]
+    #[ignore]
+    fn replaced_engines_are_not_weaker() {
+        for level in [Level::Medium, Level::Hard] {
+            let score = score_against(
+                40,
+                4,
+                |_| Box::new(AiPlayer::new(level)),
+                |_| {
+                    let negamax = Negamax::new(Eval::default(), level as u8);
+                    Box::new(Minimax(RefCell::new(negamax)))
+                },
+            );
+            println!("AiPlayer {level:?} against minimax::Negamax: {:.1}%", score * 100.0);
+            assert!(score >= 0.4, "{level:?}");
+        }
+        let score = score_against(
+            20,
+            1,
+            |_| Box::new(AiPlayerMonte::new(Level::Easy)),
+            |_| {
+                let options = MCTSOptions::default()
+                    .with_max_rollout_depth(5)
+                    .with_num_threads(4);
+                let mut mcts = MonteCarloTreeSearch::new(options);
+                mcts.set_timeout(Duration::from_millis(300));
+                Box::new(Minimax(RefCell::new(mcts)))
+            },
+        );
+        println!(
+            "AiPlayerMonte Easy against minimax::MonteCarloTreeSearch: {:.1}%",
+            score * 100.0
+        );
+        assert!(score >= 0.4);
+    }
+
+    /// `AlphaBeta` finds the values `minimax::Negamax` finds at the same depth, so `AiPlayer`
+    /// plays as strong as before and only breaks ties differently. Wins and losses compare
+    /// by their sign, as `AlphaBeta` also counts the plies to them.
+    #[test]
+    fn alpha_beta_finds_the_values_of_negamax() {
+        let outcome = |value: i16| match value {
+            v if v > BEST_EVAL - 100 => BEST_EVAL,
+            v if v < WORST_EVAL + 100 => WORST_EVAL,
+            v => v,
+        };
+        let mut rng = ChaCha8Rng::seed_from_u64(27);
+        for plies in [2, 4, 6, 8, 10, 12] {
+            let mut game = Game::new(7);
+            for _ in 0..plies {
+                if !game.game_over {
+                    game.apply_move(*game.generate_moves().choose(&mut rng).unwrap())
+                        .unwrap();
+                }
+            }
+            if game.game_over {
+                continue;
+            }
+            for depth in 1..=3 {
+                let mut alpha_beta = AlphaBeta::new(Eval::default(), depth);
+                let mut negamax = Negamax::new(Eval::default(), depth);
+                let m = alpha_beta.choose_move(&game).unwrap();
+                negamax.choose_move(&game).unwrap();
+                let value = outcome(alpha_beta.root_value());
+                let expected = outcome(negamax.root_value());
+                assert_eq!(value, expected, "{} at depth {depth}", game.board);
+                let scored = alpha_beta.score_moves(&game, vec![m]);
+                assert_eq!(outcome(scored[0].1), value);
+            }
+        }
+    }
+
+    /// `Mcts` replaced `minimax::MonteCarloTreeSearch` in `AiPlayerMonte`. Even without the
+    /// solver in front of it, it must take a Joy and stop the opponent's.
+    #[test]
+    fn mcts_takes_and_stops_joy() {
+        let mut board = Board::new(7);
+        board.change(Position(2, 2), Field::Wealth);
+        board.change(Position(2, 3), Field::Birth);
+        board.change(Position(3, 2), Field::Gift);
+        let joy = Move::new(Position(3, 3), Field::Joy);
+        let wealth = Game::from_board(board.clone(), 0);
+        let mut won = wealth.clone();
+        won.apply_move(joy).unwrap();
+        assert!(won.game_over);
+        let mut mcts = Mcts::new(MctsOptions::deterministic(29, 2_000));
+        assert_eq!(mcts.choose_move(&wealth), Some(joy));
+        let mut knowledge = Game::from_board(board, 1);
+        let m = mcts.choose_move(&knowledge).unwrap();
+        knowledge.apply_move(m).unwrap();
+        assert!(knowledge.apply_move(joy).is_err(), "{m}");
+    }
 }
@@ -0,0 +1,527 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use minimax::{Evaluation, Strategy};
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::game::{Game, Move};
+
+/// How hard UCT explores moves with few visits.
+const EXPLORATION: f64 = std::f64::consts::SQRT_2;
+
+#[derive(Clone, Debug)]
+pub struct MctsOptions {
+    /// Random moves played after leaving the tree; a rollout that doesn't end the game
+    /// counts as half a win.
+    pub max_rollout_depth: u32,
+    /// Every thread grows its own tree, the root statistics are added up at the end.
+    /// Pondering uses the same threads, never more.
+    pub threads: usize,
+    pub timeout: Duration,
+    /// Stop after this many rollouts per thread instead of after `timeout`.
+    pub max_rollouts: Option<u32>,
+    /// Seeds the random numbers of all threads; `None` takes a fresh seed.
+    /// Only with `max_rollouts` set is the search reproducible.
+    pub seed: Option<u64>,
+    /// Searches on from the trees of the last move. Without it every `choose_move` starts
+    /// afresh from `seed`, so its move depends only on the position.
+    pub keep_trees: bool,
+    /// Pondering stops growing the trees once they take this many bytes together, so a long
+    /// think of the opponent can't use up all memory.
+    pub ponder_memory: usize,
+}
+
+impl MctsOptions {
+    /// One thread, a fixed number of rollouts and no kept trees: the same seed and position
+    /// always give the same answer, whatever was played before and the speed of the machine.
+    pub fn deterministic(seed: u64, rollouts: u32) -> Self {
+        MctsOptions {
+            threads: 1,
+            max_rollouts: Some(rollouts),
+            seed: Some(seed),
+            keep_trees: false,
+            ..MctsOptions::default()
+        }
+    }
+}
+
+impl Default for MctsOptions {
+    fn default() -> Self {
+        MctsOptions {
+            max_rollout_depth: 5,
+            threads: 4,
+            timeout: Duration::from_secs(1),
+            max_rollouts: None,
+            seed: None,
+            keep_trees: true,
+            ponder_memory: 256 << 20,
+        }
+    }
+}
+
+/// Statistics of one move at the root of the last search.
+#[derive(Clone, Copy, Debug)]
+pub struct MoveStats {
+    pub mv: Move,
+    pub visits: u32,
+    /// Average result for the side to move: 1 is a sure win, 0 a sure loss.
+    pub win_rate: f64,
+}
+
+impl MoveStats {
+    /// The win rate on the scale of evaluations, from -1000 (lost) to 1000 (won).
+    pub fn score(&self) -> Evaluation {
+        ((self.win_rate * 2.0 - 1.0) * 1000.0).round() as Evaluation
+    }
+}
+
+/// Monte Carlo Tree Search with random rollouts.
+///
+/// Unlike `minimax::MonteCarloTreeSearch` it keeps the statistics of the root moves,
+/// so they can be shown as an analysis, and it keeps its trees between moves: the part below
+/// the moves actually played is searched on at the next `choose_move`. With the same time it
+/// plays about as well, see the ignored test `replaced_engines_are_not_weaker`; the test
+/// `mcts_takes_and_stops_joy` checks it still sees the tactics on every run.
+pub struct Mcts {
+    pub options: MctsOptions,
+    root_stats: Vec<MoveStats>,
+    pv: Vec<Move>,
+    /// Trees of the last search or pondering, all rooted at `root`.
+    trees: Vec<Tree>,
+    root: Option<Game>,
+    pondering: Option<Pondering>,
+    /// Hands out the seeds of the search threads.
+    rng: ChaCha8Rng,
+    rollouts: u64,
+    stop: Arc<AtomicBool>,
+}
+
+struct Pondering {
+    stop: Arc<AtomicBool>,
+    handles: Vec<JoinHandle<Tree>>,
+    root: Game,
+}
+
+struct Node {
+    mv: Option<Move>,
+    children: Vec<usize>,
+    /// Filled when the node is first expanded.
+    untried: Option<Vec<Move>>,
+    visits: u32,
+    /// Sum of results for the player who made `mv`.
+    score: f64,
+}
+
+impl Node {
+    fn new(mv: Option<Move>) -> Self {
+        Node {
+            mv,
+            children: Vec::new(),
+            untried: None,
+            visits: 0,
+            score: 0.0,
+        }
+    }
+
+    fn uct(&self, parent_visits: u32) -> f64 {
+        self.score / self.visits as f64
+            + EXPLORATION * ((parent_visits as f64).ln() / self.visits as f64).sqrt()
+    }
+}
+
+struct Tree {
+    nodes: Vec<Node>,
+    max_rollout_depth: u32,
+    /// Room for moves in the `untried` lists of the nodes.
+    untried_capacity: usize,
+}
+
+impl Tree {
+    fn new(max_rollout_depth: u32) -> Self {
+        Tree {
+            nodes: vec![Node::new(None)],
+            max_rollout_depth,
+            untried_capacity: 0,
+        }
+    }
+
+    /// About the bytes held by the nodes and their lists.
+    fn memory(&self) -> usize {
+        // Every node but the root is in the `children` of its parent.
+        self.nodes.len() * (size_of::<Node>() + size_of::<usize>())
+            + self.untried_capacity * size_of::<Move>()
+    }
+
+    /// Returns the result of the simulation for the player who moved into `idx`.
+    fn simulate(&mut self, idx: usize, game: &mut Game, rng: &mut ChaCha8Rng) -> f64 {
+        if game.game_over {
+            let result = outcome(game, 1 - game.next_player);
+            self.update(idx, result);
+            return result;
+        }
+        if self.nodes[idx].untried.is_none() {
+            let moves = game.generate_moves();
+            self.untried_capacity += moves.capacity();
+            self.nodes[idx].untried = Some(moves);
+        }
+        let untried = self.nodes[idx].untried.as_mut().unwrap();
+        let result = if !untried.is_empty() {
+            let m = untried.swap_remove(rng.gen_range(0..untried.len()));
+            if untried.is_empty() {
+                // Fully expanded, the list is kept empty without its room.
+                self.untried_capacity -= untried.capacity();
+                *untried = Vec::new();
+            }
+            game.apply_move(m).unwrap();
+            let child = self.nodes.len();
+            self.nodes.push(Node::new(Some(m)));
+            self.nodes[idx].children.push(child);
+            let result = self.rollout(game, rng);
+            self.update(child, result);
+            1.0 - result
+        } else if let Some(child) = self.best_child(idx) {
+            game.apply_move(self.nodes[child].mv.unwrap()).unwrap();
+            1.0 - self.simulate(child, game, rng)
+        } else {
+            0.5
+        };
+        self.update(idx, result);
+        result
+    }
+
+    fn update(&mut self, idx: usize, result: f64) {
+        self.nodes[idx].visits += 1;
+        self.nodes[idx].score += result;
+    }
+
+    fn best_child(&self, idx: usize) -> Option<usize> {
+        let visits = self.nodes[idx].visits;
+        self.nodes[idx].children.iter().copied().max_by(|&a, &b| {
+            self.nodes[a]
+                .uct(visits)
+                .total_cmp(&self.nodes[b].uct(visits))
+        })
+    }
+
+    fn most_visited_child(&self, idx: usize) -> Option<usize> {
+        self.nodes[idx]
+            .children
+            .iter()
+            .copied()
+            .max_by_key(|&c| self.nodes[c].visits)
+    }
+
+    /// Copy of the part of the tree below `idx`, with `idx` as the new root.
+    fn subtree(&self, idx: usize) -> Tree {
+        let mut tree = Tree {
+            nodes: Vec::new(),
+            max_rollout_depth: self.max_rollout_depth,
+            untried_capacity: 0,
+        };
+        self.copy_into(idx, &mut tree.nodes);
+        tree.untried_capacity = tree
+            .nodes
+            .iter()
+            .map(|node| node.untried.as_ref().map_or(0, Vec::capacity))
+            .sum();
+        tree
+    }
+
+    fn copy_into(&self, idx: usize, nodes: &mut Vec<Node>) -> usize {
+        let node = &self.nodes[idx];
+        let new_idx = nodes.len();
+        nodes.push(Node {
+            mv: node.mv,
+            children: Vec::new(),
+            untried: node.untried.clone(),
+            visits: node.visits,
+            score: node.score,
+        });
+        for &child in &node.children {
+            let new_child = self.copy_into(child, nodes);
+            nodes[new_idx].children.push(new_child);
+        }
+        new_idx
+    }
+
+    /// The node reached from the root (where the game is `root`) by the moves that lead to
+    /// `target`, looking at most `plies` moves deep.
+    fn find(&self, idx: usize, root: &Game, target: &Game, plies: usize) -> Option<usize> {
+        if same_position(root, target) {
+            return Some(idx);
+        }
+        if plies == 0 {
+            return None;
+        }
+        self.nodes[idx].children.iter().find_map(|&child| {
+            let mut game = root.clone();
+            game.apply_move(self.nodes[child].mv.unwrap()).ok()?;
+            self.find(child, &game, target, plies - 1)
+        })
+    }
+
+    /// Plays random moves from `game`; the result is for the player who just moved.
+    fn rollout(&self, game: &Game, rng: &mut ChaCha8Rng) -> f64 {
+        let mover = 1 - game.next_player;
+        let mut game = game.clone();
+        for _ in 0..self.max_rollout_depth {
+            if game.game_over {
+                break;
+            }
+            let Some(&m) = game.generate_moves().choose(rng) else {
+                break;
+            };
+            game.apply_move(m).unwrap();
+        }
+        outcome(&game, mover)
+    }
+}
+
+fn same_position(a: &Game, b: &Game) -> bool {
+    a.board == b.board && a.next_player == b.next_player && a.game_over == b.game_over
+}
+
+fn outcome(game: &Game, player: u8) -> f64 {
+    match game.winner() {
+        Some(winner) if winner == player => 1.0,
+        Some(_) => 0.0,
+        None => 0.5,
+    }
+}
+
+impl Mcts {
+    pub fn new(options: MctsOptions) -> Self {
+        let rng = match options.seed {
+            Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+            None => ChaCha8Rng::from_entropy(),
+        };
+        Mcts {
+            options,
+            rng,
+            rollouts: 0,
+            stop: Arc::new(AtomicBool::new(false)),
+            root_stats: Vec::new(),
+            pv: Vec::new(),
+            trees: Vec::new(),
+            root: None,
+            pondering: None,
+        }
+    }
+
+    /// The moves of the last search, most visited first.
+    pub fn root_stats(&self) -> &[MoveStats] {
+        &self.root_stats
+    }
+
+    /// Setting the flag from another thread ends the running `choose_move` early, after at least
+    /// one rollout. The search clears the flag when it returns.
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    /// Rollouts of the last search on all threads, without those done while pondering.
+    pub fn rollouts(&self) -> u64 {
+        self.rollouts
+    }
+
+    /// Nodes in all trees, including those kept from earlier searches.
+    pub fn tree_size(&self) -> usize {
+        self.trees.iter().map(|tree| tree.nodes.len()).sum()
+    }
+
+    /// About the bytes taken by all trees, which pondering keeps below `ponder_memory`.
+    pub fn tree_memory(&self) -> usize {
+        self.trees.iter().map(Tree::memory).sum()
+    }
+
+    /// Keeps searching `game`, where the opponent is to move, in the background until
+    /// `stop_pondering` or the next `choose_move`, which then starts from what was found.
+    pub fn ponder(&mut self, game: &Game) {
+        self.stop_pondering();
+        if game.game_over {
+            return;
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        let trees = self.take_trees(game);
+        let max_memory = self.options.ponder_memory / trees.len();
+        let handles = trees
+            .into_iter()
+            .map(|mut tree| {
+                let stop = stop.clone();
+                let game = game.clone();
+                let mut rng = ChaCha8Rng::seed_from_u64(self.rng.gen());
+                thread::spawn(move || {
+                    while !stop.load(Ordering::Relaxed) && tree.memory() < max_memory {
+                        tree.simulate(0, &mut game.clone(), &mut rng);
+                    }
+                    tree
+                })
+            })
+            .collect();
+        self.pondering = Some(Pondering {
+            stop,
+            handles,
+            root: game.clone(),
+        });
+    }
+
+    /// Stops the background search and waits for its threads, which takes at most
+    /// one simulation.
+    pub fn stop_pondering(&mut self) {
+        if let Some(pondering) = self.pondering.take() {
+            pondering.stop.store(true, Ordering::Relaxed);
+            self.trees = pondering
+                .handles
+                .into_iter()
+                .map(|h| h.join().unwrap())
+                .collect();
+            self.root = Some(pondering.root);
+        }
+    }
+
+    pub fn is_pondering(&self) -> bool {
+        self.pondering.is_some()
+    }
+
+    /// One tree per thread for `game`: the kept trees where they reach `game`, new ones
+    /// for the rest.
+    fn take_trees(&mut self, game: &Game) -> Vec<Tree> {
+        let mut trees: Vec<Tree> = match self.root.take() {
+            Some(root) => std::mem::take(&mut self.trees)
+                .into_iter()
+                .filter_map(|tree| Some(tree.subtree(tree.find(0, &root, game, 2)?)))
+                .collect(),
+            None => Vec::new(),
+        };
+        let threads = self.options.threads.max(1);
+        trees.truncate(threads);
+        trees.resize_with(threads, || Tree::new(self.options.max_rollout_depth));
+        trees
+    }
+
+    /// Returns the number of rollouts.
+    fn grow(&self, tree: &mut Tree, game: &Game, seed: u64) -> u32 {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let deadline = Instant::now() + self.options.timeout;
+        let mut rollouts = 0;
+        loop {
+            match self.options.max_rollouts {
+                Some(max) if rollouts >= max => break,
+                None if Instant::now() >= deadline => break,
+                _ if rollouts > 0 && self.stop.load(Ordering::Relaxed) => break,
+                _ => (),
+            }
+            tree.simulate(0, &mut game.clone(), &mut rng);
+            rollouts += 1;
+        }
+        rollouts
+    }
+}
+
+impl Drop for Mcts {
+    fn drop(&mut self) {
+        if let Some(pondering) = &self.pondering {
+            pondering.stop.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Strategy<Game> for Mcts {
+    fn choose_move(&mut self, game: &Game) -> Option<Move> {
+        self.stop_pondering();
+        if game.game_over {
+            return None;
+        }
+        if !self.options.keep_trees {
+            self.trees.clear();
+            self.root = None;
+            if let Some(seed) = self.options.seed {
+                self.rng = ChaCha8Rng::seed_from_u64(seed);
+            }
+        }
+        let trees = self.take_trees(game);
+        let seeds: Vec<u64> = trees.iter().map(|_| self.rng.gen()).collect();
+        let this = &*self;
+        let (trees, rollouts): (Vec<Tree>, Vec<u32>) = thread::scope(|scope| {
+            let handles: Vec<_> = trees
+                .into_iter()
+                .zip(seeds)
+                .map(|(mut tree, seed)| {
+                    scope.spawn(move || {
+                        let rollouts = this.grow(&mut tree, game, seed);
+                        (tree, rollouts)
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).unzip()
+        });
+        self.rollouts = rollouts.into_iter().map(u64::from).sum();
+        self.stop.store(false, Ordering::Relaxed);
+
+        let mut stats: Vec<(Move, u32, f64)> = Vec::new();
+        for tree in &trees {
+            for &child in &tree.nodes[0].children {
+                let node = &tree.nodes[child];
+                let mv = node.mv.unwrap();
+                match stats.iter_mut().find(|(m, _, _)| *m == mv) {
+                    Some(entry) => {
+                        entry.1 += node.visits;
+                        entry.2 += node.score;
+                    }
+                    None => stats.push((mv, node.visits, node.score)),
+                }
+            }
+        }
+        stats.sort_by_key(|&(_, visits, _)| std::cmp::Reverse(visits));
+        self.root_stats = stats
+            .into_iter()
+            .map(|(mv, visits, score)| MoveStats {
+                mv,
+                visits,
+                win_rate: score / visits.max(1) as f64,
+            })
+            .collect();
+
+        // Continue the best move in the tree that looked at it most.
+        self.pv.clear();
+        if let Some(best) = self.root_stats.first() {
+            let (tree, mut idx) = trees
+                .iter()
+                .filter_map(|tree| {
+                    let child = tree.nodes[0]
+                        .children
+                        .iter()
+                        .copied()
+                        .find(|&c| tree.nodes[c].mv == Some(best.mv))?;
+                    Some((tree, child))
+                })
+                .max_by_key(|(tree, child)| tree.nodes[*child].visits)
+                .unwrap();
+            self.pv.push(best.mv);
+            while let Some(child) = tree.most_visited_child(idx) {
+                self.pv.push(tree.nodes[child].mv.unwrap());
+                idx = child;
+            }
+        }
+
+        self.trees = trees;
+        self.root = Some(game.clone());
+        self.root_stats.first().map(|s| s.mv)
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) {
+        self.options.timeout = timeout;
+        self.options.max_rollouts = None;
+    }
+
+    fn principal_variation(&self) -> Vec<Move> {
+        self.pv.clone()
+    }
+}
@@ -0,0 +1,43 @@
+use crate::game::{Game, Move};
+
+/// `perft` from `Game::new(size)` for depths 1, 2, ...: the counts of the current rules.
+/// A change to the rules or the board that changes them is a change of the game.
+pub const REFERENCE_COUNTS: [(usize, &[u64]); 2] = [
+    (5, &[24, 576, 13_944, 340_208, 8_278_024, 199_287_000]),
+    (7, &[48, 2_280, 108_096, 5_147_792, 247_028_200]),
+];
+
+/// Number of move sequences of length `depth` from `game` over `Game::legal_moves`;
+/// finished games end a sequence early and don't count.
+pub fn perft(game: &Game, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = game.legal_moves();
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    moves
+        .into_iter()
+        .map(|m| {
+            let mut next = game.clone();
+            next.apply_move(m).unwrap();
+            perft(&next, depth - 1)
+        })
+        .sum()
+}
+
+/// `perft` split up by the first move, in the order of `Game::legal_moves`.
+pub fn divide(game: &Game, depth: usize) -> Vec<(Move, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+    game.legal_moves()
+        .into_iter()
+        .map(|m| {
+            let mut next = game.clone();
+            next.apply_move(m).unwrap();
+            (m, perft(&next, depth - 1))
+        })
+        .collect()
+}
@@ -0,0 +1,117 @@
+use std::{fmt::Display, str::FromStr, time::Duration};
+
+use crate::{
+    ai_player::{AiPlayer, AiPlayerCasual, AiPlayerMonte, AiPlayerRandom, Level},
+    external_player::ExternalPlayer,
+    game::Player,
+    mcts::MctsOptions,
+};
+
+/// Thinking time of an engine when the spec doesn't give one.
+const DEFAULT_MOVETIME: Duration = Duration::from_secs(1);
+
+/// Describes a computer player on the command line, e.g. `mcts:hard`, `mcts:500ms`,
+/// `negamax`, `casual:easy`, `random` or `engine:./my_bot@200ms`.
+/// Without a level `medium` is used.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PlayerSpec {
+    Random,
+    Negamax(Level),
+    Casual(Level),
+    Mcts(Level),
+    /// Tree search for a fixed time per move.
+    MctsTime(Duration),
+    /// An engine executable speaking the `runes_engine` protocol.
+    Engine {
+        program: String,
+        movetime: Duration,
+    },
+}
+
+impl PlayerSpec {
+    /// Creates the player; a seed makes the random choices of the built-in AIs repeatable.
+    pub fn create(&self, seed: Option<u64>) -> Result<Box<dyn Player>, String> {
+        Ok(match self {
+            PlayerSpec::Random => Box::new(AiPlayerRandom::with_seed(Level::Easy, seed)),
+            PlayerSpec::Negamax(level) => Box::new(AiPlayer::new(*level)),
+            PlayerSpec::Casual(level) => Box::new(AiPlayerCasual::with_seed(*level, seed)),
+            PlayerSpec::Mcts(level) => Box::new(AiPlayerMonte::with_seed(*level, seed)),
+            PlayerSpec::MctsTime(time) => Box::new(AiPlayerMonte::with_options(
+                Level::Medium,
+                MctsOptions {
+                    timeout: *time,
+                    seed,
+                    ..MctsOptions::default()
+                },
+            )),
+            PlayerSpec::Engine { program, movetime } => {
+                Box::new(ExternalPlayer::new(program, &[], *movetime)?)
+            }
+        })
+    }
+}
+
+fn parse_time(s: &str) -> Option<Duration> {
+    if let Some(ms) = s.strip_suffix("ms") {
+        ms.parse().ok().map(Duration::from_millis)
+    } else {
+        s.strip_suffix('s')?
+            .parse()
+            .ok()
+            .map(Duration::from_secs_f64)
+    }
+}
+
+impl FromStr for PlayerSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, arg) = match s.split_once(':') {
+            Some((kind, arg)) => (kind, Some(arg)),
+            None => (s, None),
+        };
+        let level = || arg.map_or(Ok(Level::Medium), str::parse);
+        match kind.to_lowercase().as_str() {
+            "random" if arg.is_none() => Ok(PlayerSpec::Random),
+            "negamax" => Ok(PlayerSpec::Negamax(level()?)),
+            "casual" => Ok(PlayerSpec::Casual(level()?)),
+            "mcts" => match arg.and_then(parse_time) {
+                Some(time) => Ok(PlayerSpec::MctsTime(time)),
+                None => Ok(PlayerSpec::Mcts(level()?)),
+            },
+            "engine" => {
+                let arg = arg
+                    .filter(|a| !a.is_empty())
+                    .ok_or("Missing engine program")?;
+                Ok(match arg.rsplit_once('@') {
+                    Some((program, time)) => PlayerSpec::Engine {
+                        program: program.to_string(),
+                        movetime: parse_time(time).ok_or(format!("Invalid time `{time}`"))?,
+                    },
+                    None => PlayerSpec::Engine {
+                        program: arg.to_string(),
+                        movetime: DEFAULT_MOVETIME,
+                    },
+                })
+            }
+            _ => Err(format!("Unknown player `{s}`")),
+        }
+    }
+}
+
+/// Writes the spec back in the form `FromStr` reads.
+impl Display for PlayerSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let level = |level: &Level| format!("{level:?}").to_lowercase();
+        match self {
+            PlayerSpec::Random => write!(f, "random"),
+            PlayerSpec::Negamax(l) => write!(f, "negamax:{}", level(l)),
+            PlayerSpec::Casual(l) => write!(f, "casual:{}", level(l)),
+            PlayerSpec::Mcts(l) => write!(f, "mcts:{}", level(l)),
+            PlayerSpec::MctsTime(time) => write!(f, "mcts:{}ms", time.as_millis()),
+            PlayerSpec::Engine { program, movetime } => {
+                write!(f, "engine:{program}@{}ms", movetime.as_millis())
+            }
+        }
+    }
+}
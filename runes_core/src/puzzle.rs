@@ -0,0 +1,167 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{
+    game::{Game, Move},
+    record::{parse_start, start_text},
+    solver,
+};
+
+/// A position where the side to move forces a Joy in `joy_in` of its own moves, with exactly
+/// one first move that does it.
+///
+/// Puzzle files hold one puzzle per block of `Key: value` lines, blocks are separated by an
+/// empty line:
+///
+/// ```text
+/// Joy in: 2
+/// Start: ....B..../........./.......B./......X../....B..../........./........./....B..../......... W
+/// Solution: f4=W f2=X f5=J
+/// ```
+///
+/// `Start` is written like the header of a `GameRecord`. The solution starts with the key move,
+/// alternates with the defender's most stubborn replies and ends with the Joy.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Puzzle {
+    pub start: Game,
+    pub joy_in: usize,
+    pub solution: Vec<Move>,
+}
+
+impl Puzzle {
+    /// The puzzle in `game` if the side to move has a unique forced Joy in at most
+    /// `max_moves`: no other legal move, quiet ones included, wins as fast. Positions with
+    /// a Joy on the spot are too easy to count.
+    pub fn find(game: &Game, max_moves: usize) -> Option<Puzzle> {
+        if game.game_over || !game.joy_squares(game.next_player_symbol()).is_empty() {
+            return None;
+        }
+        let (joy_in, solution) =
+            (2..=max_moves).find_map(|n| Some((n, solver::forced_joy(game, n)?)))?;
+        if solver::winning_moves(game, joy_in) != solution[..1] {
+            return None;
+        }
+        Some(Puzzle {
+            start: Game::from_board(game.board.clone(), game.next_player),
+            joy_in,
+            solution,
+        })
+    }
+
+    /// Does `m` keep the forced Joy after the moves `played` since the start? Other moves
+    /// than the solution's count as well if the solver finds they still win in time.
+    pub fn accepts(&self, played: &[Move], m: Move) -> bool {
+        let Ok(mut game) = self.position_after(played) else {
+            return false;
+        };
+        let used = played.len().div_ceil(2);
+        let attacker = self.start.next_player;
+        if game.next_player != attacker || used >= self.joy_in || game.apply_move(m).is_err() {
+            return false;
+        }
+        game.winner() == Some(attacker)
+            || solver::unstoppable_joy(&game, self.joy_in - used - 1).is_some()
+    }
+
+    /// The defender's answer to the moves `played`: the solution's reply while it was
+    /// followed, otherwise the longest defence the solver finds.
+    pub fn reply(&self, played: &[Move]) -> Option<Move> {
+        let game = self.position_after(played).ok()?;
+        if game.next_player == self.start.next_player {
+            return None;
+        }
+        if let Some(&m) = self.solution.get(played.len()) {
+            if self.solution.starts_with(played) {
+                return Some(m);
+            }
+        }
+        let left = self.joy_in.saturating_sub(played.len().div_ceil(2));
+        solver::unstoppable_joy(&game, left)
+            .and_then(|line| line.first().copied())
+            .or_else(|| game.generate_moves().first().copied())
+    }
+
+    /// The position after the moves `played` since the start.
+    pub fn position_after(&self, played: &[Move]) -> Result<Game, String> {
+        let mut game = self.start.clone();
+        for &m in played {
+            game.apply_move(m)?;
+        }
+        Ok(game)
+    }
+}
+
+/// Reads a puzzle file.
+pub fn parse_puzzles(text: &str) -> Result<Vec<Puzzle>, String> {
+    text.split("\n\n")
+        .filter(|block| !block.trim().is_empty())
+        .enumerate()
+        .map(|(i, block)| block.parse().map_err(|e| format!("Puzzle {}: {e}", i + 1)))
+        .collect()
+}
+
+/// Writes puzzles the way `parse_puzzles` reads them.
+pub fn write_puzzles(puzzles: &[Puzzle]) -> String {
+    puzzles
+        .iter()
+        .map(Puzzle::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl Display for Puzzle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let solution: Vec<String> = self.solution.iter().map(|m| format!("{m}")).collect();
+        writeln!(f, "Joy in: {}", self.joy_in)?;
+        writeln!(f, "Start: {}", start_text(&self.start))?;
+        writeln!(f, "Solution: {}", solution.join(" "))
+    }
+}
+
+impl FromStr for Puzzle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut joy_in = None;
+        let mut start = None;
+        let mut solution = None;
+        for line in s.lines().filter(|l| !l.trim().is_empty()) {
+            let (key, value) = line
+                .split_once(':')
+                .ok_or(format!("Expected `Key: value`, got `{line}`"))?;
+            let value = value.trim();
+            match key.trim() {
+                "Joy in" => {
+                    joy_in = Some(
+                        value
+                            .parse::<usize>()
+                            .map_err(|_| format!("Invalid number `{value}`"))?,
+                    )
+                }
+                "Start" => start = Some(parse_start(value)?),
+                "Solution" => {
+                    solution = Some(
+                        value
+                            .split_whitespace()
+                            .map(str::parse)
+                            .collect::<Result<Vec<Move>, _>>()?,
+                    )
+                }
+                _ => (),
+            }
+        }
+        let puzzle = Puzzle {
+            start: start.ok_or("Missing Start")?,
+            joy_in: joy_in.ok_or("Missing Joy in")?,
+            solution: solution.ok_or("Missing Solution")?,
+        };
+        let mut game = puzzle.start.clone();
+        for m in &puzzle.solution {
+            game.apply_move(*m)
+                .map_err(|e| format!("{e} {m} in the solution"))?;
+        }
+        if game.winner() != Some(puzzle.start.next_player) {
+            return Err("The solution doesn't end with a Joy".to_string());
+        }
+        Ok(puzzle)
+    }
+}
@@ -0,0 +1,184 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{
+    board::Board,
+    game::{Game, Move},
+    session::Session,
+};
+
+/// A game as text: `Key: value` header lines, an empty line, then one move per line.
+/// Anything after `#` on a move line is a comment, e.g. what the engine thought.
+///
+/// ```text
+/// Size: 9
+/// Wealth: mcts:medium
+/// Knowledge: random
+/// Result: 1-0
+///
+/// d4=X  # score +12 playouts 812 nodes 820 time 300ms pv d4=X f6=X
+/// f6=X
+/// ```
+///
+/// `Size` is required. A `Start` header holds the board notation and the side to move of a
+/// game that didn't start from `Game::new`. `Rules: pie` marks games with the pie rule, where
+/// the second move may be `swap`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GameRecord {
+    pub headers: Vec<(String, String)>,
+    pub moves: Vec<Move>,
+    /// One per move, empty for moves without a comment.
+    pub comments: Vec<String>,
+}
+
+impl GameRecord {
+    /// A record of the moves played from `start`.
+    pub fn new(start: &Game, moves: Vec<Move>) -> Self {
+        let mut record = GameRecord {
+            comments: vec![String::new(); moves.len()],
+            moves,
+            ..GameRecord::default()
+        };
+        let size = start.board.size;
+        record.set_header("Size", &size.to_string());
+        let fresh = Game::new(size);
+        if start.board != fresh.board || start.next_player != fresh.next_player {
+            record.set_header("Start", &start_text(start));
+        }
+        if start.pie_rule {
+            record.set_header("Rules", "pie");
+        }
+        record
+    }
+
+    /// The moves of `session` with the player names, the result and how the game ended.
+    pub fn from_session(session: &Session) -> Self {
+        let mut record = GameRecord::new(&session.start, session.moves.clone());
+        record.set_header("Wealth", &session.players[0].name());
+        record.set_header("Knowledge", &session.players[1].name());
+        let result = match session.winner_index() {
+            Some(0) => "1-0",
+            Some(_) => "0-1",
+            None if session.game.game_over => "1/2-1/2",
+            None => "*",
+        };
+        record.set_header("Result", result);
+        if let Some(forfeit) = &session.forfeit {
+            record.set_header("Termination", &format!("forfeit: {}", forfeit.reason));
+        } else if session.game.winner().is_some() {
+            record.set_header("Termination", "joy");
+        } else if session.game.game_over {
+            record.set_header("Termination", "full board");
+        }
+        record
+    }
+
+    pub fn header(&self, key: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Replaces the header `key` or adds it at the end.
+    pub fn set_header(&mut self, key: &str, value: &str) {
+        match self.headers.iter_mut().find(|(k, _)| k == key) {
+            Some(header) => header.1 = value.to_string(),
+            None => self.headers.push((key.to_string(), value.to_string())),
+        }
+    }
+
+    /// The position before the first move.
+    pub fn start(&self) -> Result<Game, String> {
+        let size: usize = self
+            .header("Size")
+            .ok_or("Missing Size header")?
+            .parse()
+            .map_err(|_| "Invalid Size header".to_string())?;
+        let pie_rule = self.header("Rules") == Some("pie");
+        let Some(start) = self.header("Start") else {
+            let mut game = Game::new(size);
+            game.pie_rule = pie_rule;
+            return Ok(game);
+        };
+        let mut game = parse_start(start)?;
+        if game.board.size != size {
+            return Err("The Start board doesn't match the Size header".to_string());
+        }
+        game.pie_rule = pie_rule;
+        Ok(game)
+    }
+
+    /// The position after all moves.
+    pub fn replay(&self) -> Result<Game, String> {
+        let mut game = self.start()?;
+        for (i, m) in self.moves.iter().enumerate() {
+            game.apply_move(*m)
+                .map_err(|e| format!("{e} {m} (move {})", i + 1))?;
+        }
+        Ok(game)
+    }
+}
+
+/// A position as in a `Start` header: the board notation and the side to move, `W` or `K`.
+pub fn start_text(game: &Game) -> String {
+    let side = game.next_player_symbol().letter();
+    format!("{} {side}", game.board.notation())
+}
+
+/// Reads what `start_text` writes.
+pub fn parse_start(text: &str) -> Result<Game, String> {
+    let (board, side) = text
+        .rsplit_once(' ')
+        .ok_or(format!("Invalid start position `{text}`"))?;
+    let board: Board = board.parse()?;
+    let next_player = match side {
+        "W" => 0,
+        "K" => 1,
+        _ => return Err(format!("Unknown side to move `{side}`")),
+    };
+    Ok(Game::from_board(board, next_player))
+}
+
+impl Display for GameRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (key, value) in &self.headers {
+            writeln!(f, "{key}: {value}")?;
+        }
+        writeln!(f)?;
+        for (i, m) in self.moves.iter().enumerate() {
+            match self.comments.get(i).filter(|c| !c.is_empty()) {
+                Some(comment) => writeln!(f, "{m}  # {comment}")?,
+                None => writeln!(f, "{m}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for GameRecord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut record = GameRecord::default();
+        let mut lines = s.lines().enumerate();
+        for (i, line) in lines.by_ref() {
+            if line.trim().is_empty() {
+                break;
+            }
+            let (key, value) = line
+                .split_once(':')
+                .ok_or(format!("Line {}: expected `Key: value`", i + 1))?;
+            record.set_header(key.trim(), value.trim());
+        }
+        for (i, line) in lines {
+            let (m, comment) = line.split_once('#').unwrap_or((line, ""));
+            if m.trim().is_empty() {
+                continue;
+            }
+            let m: Move = m.parse().map_err(|e| format!("Line {}: {e}", i + 1))?;
+            record.moves.push(m);
+            record.comments.push(comment.trim().to_string());
+        }
+        Ok(record)
+    }
+}
@@ -0,0 +1,139 @@
+use std::io::IsTerminal;
+
+use crate::board::{column_name, Board, Field, Position};
+
+/// How the fields of a board are drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderStyle {
+    /// The letters of the board notation, `. B X W K J`.
+    Plain,
+    /// The runes themselves, `. ᛒ X ᚠ < ᚹ`.
+    Runes,
+    /// The letters in terminal colors, plain letters without the `colorize` feature.
+    Ansi,
+}
+
+/// The style of `for_terminal` in color terminals.
+#[cfg(feature = "colorize")]
+const COLOR_STYLE: RenderStyle = RenderStyle::Ansi;
+#[cfg(not(feature = "colorize"))]
+const COLOR_STYLE: RenderStyle = RenderStyle::Runes;
+
+/// `text` in the SGR style `code`, e.g. `32` for green, followed by a reset.
+#[cfg(feature = "colorize")]
+fn sgr(code: &str, text: &str) -> String {
+    format!("\x1b[{code}m{text}\x1b[0m")
+}
+
+/// The style of `for_terminal`: `NO_COLOR` and output that isn't a terminal get runes.
+pub(crate) fn terminal_style(no_color: bool, terminal: bool) -> RenderStyle {
+    if no_color || !terminal {
+        RenderStyle::Runes
+    } else {
+        COLOR_STYLE
+    }
+}
+
+/// Draws boards as text, one row per line.
+///
+/// ```text
+///   a b c d e
+/// 1 . . . . .
+/// 2 . . . X .
+/// 3 . . B W .
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoardRenderer {
+    pub style: RenderStyle,
+    /// Column letters and row numbers around the board, as in `Position`'s `g7`.
+    pub labels: bool,
+}
+
+impl Default for BoardRenderer {
+    fn default() -> Self {
+        BoardRenderer {
+            style: RenderStyle::Plain,
+            labels: true,
+        }
+    }
+}
+
+impl BoardRenderer {
+    pub fn new(style: RenderStyle, labels: bool) -> Self {
+        BoardRenderer { style, labels }
+    }
+
+    /// Colors when standard output is a terminal and `NO_COLOR` isn't set, runes otherwise.
+    pub fn for_terminal() -> Self {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        BoardRenderer::new(terminal_style(no_color, std::io::stdout().is_terminal()), true)
+    }
+
+    /// One field, a single character wide on the screen.
+    pub fn field(&self, field: Field) -> String {
+        match self.style {
+            RenderStyle::Plain => field.letter().to_string(),
+            RenderStyle::Runes => match field {
+                Field::Empty => ".",
+                Field::Birth => "ᛒ",
+                Field::Gift => "X",
+                Field::Wealth => "ᚠ",
+                Field::Knowledge => "<",
+                Field::Joy => "ᚹ",
+            }
+            .to_string(),
+            #[cfg(feature = "colorize")]
+            RenderStyle::Ansi => {
+                let letter = field.letter().to_string();
+                match field {
+                    Field::Empty | Field::Birth => letter,
+                    Field::Gift => sgr("32", &letter),
+                    Field::Wealth => sgr("33", &letter),
+                    Field::Knowledge => sgr("34", &letter),
+                    // On a bright red background.
+                    Field::Joy => sgr("101", &letter),
+                }
+            }
+            #[cfg(not(feature = "colorize"))]
+            RenderStyle::Ansi => field.letter().to_string(),
+        }
+    }
+
+    fn label(&self, text: String) -> String {
+        match self.style {
+            #[cfg(feature = "colorize")]
+            RenderStyle::Ansi => sgr("1;36", &text),
+            _ => text,
+        }
+    }
+
+    pub fn render(&self, board: &Board) -> String {
+        let label = board.size.to_string().len();
+        // Every column is as wide as the longest column name.
+        let width = column_name(board.size.saturating_sub(1)).len();
+        let mut text = String::new();
+        if self.labels {
+            let names: Vec<String> = (0..board.size)
+                .map(|j| format!("{:>width$}", column_name(j)))
+                .collect();
+            let header = format!("{:label$} {}", "", names.join(" "));
+            text.push_str(&self.label(header));
+            text.push('\n');
+        }
+        for i in 0..board.size {
+            if self.labels {
+                text.push_str(&self.label(format!("{:>label$}", i + 1)));
+                text.push(' ');
+            }
+            let fields: Vec<String> = (0..board.size)
+                .map(|j| {
+                    let field = self.field(board.field_at(&Position(i, j)));
+                    format!("{}{field}", " ".repeat(width - 1))
+                })
+                .collect();
+            text.push_str(&fields.join(" "));
+            text.push('\n');
+        }
+        text
+    }
+}
@@ -0,0 +1,334 @@
+use std::fmt::Write;
+
+use minimax::{Evaluation, BEST_EVAL};
+
+use crate::{
+    ai_player::FORCED_JOY_DEPTH,
+    analysis::{analyze, CandidateMove, Engine, Limits},
+    board::{Board, Field, Position},
+    game::{Game, Move},
+    record::GameRecord,
+    solver,
+};
+
+/// How bad a move was, by how much it dropped the mover's evaluation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Judgement {
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl Judgement {
+    /// The usual annotation symbol.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Judgement::Inaccuracy => "?!",
+            Judgement::Mistake => "?",
+            Judgement::Blunder => "??",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Judgement::Inaccuracy => "inaccuracy",
+            Judgement::Mistake => "mistake",
+            Judgement::Blunder => "blunder",
+        }
+    }
+}
+
+/// The smallest evaluation drops that make a move an inaccuracy, a mistake or a blunder.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Thresholds {
+    pub inaccuracy: Evaluation,
+    pub mistake: Evaluation,
+    pub blunder: Evaluation,
+}
+
+impl Thresholds {
+    /// Drops that suit the scores of `engine`: evaluation points for negamax, where only a
+    /// lost win is a blunder, and win rates for MCTS.
+    pub fn for_engine(engine: &Engine) -> Self {
+        match engine {
+            Engine::Negamax { .. } => Thresholds {
+                inaccuracy: 3,
+                mistake: 6,
+                blunder: 1_000,
+            },
+            Engine::Mcts { .. } => Thresholds {
+                inaccuracy: 100,
+                mistake: 250,
+                blunder: 500,
+            },
+        }
+    }
+
+    pub fn judge(&self, drop: i32) -> Option<Judgement> {
+        if drop >= self.blunder as i32 {
+            Some(Judgement::Blunder)
+        } else if drop >= self.mistake as i32 {
+            Some(Judgement::Mistake)
+        } else if drop >= self.inaccuracy as i32 {
+            Some(Judgement::Inaccuracy)
+        } else {
+            None
+        }
+    }
+}
+
+/// What the engine thinks of one move of a game. Scores are seen from the player who moved.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MoveReview {
+    pub mv: Move,
+    /// The score of the best move in the position.
+    pub before: Evaluation,
+    /// The score of the position the move led to.
+    pub after: Evaluation,
+    /// The engine's line when it prefers another move.
+    pub best: Option<CandidateMove>,
+    pub judgement: Option<Judgement>,
+    /// A forced Joy the player had and no longer has after the move.
+    pub missed_joy: Option<Vec<Move>>,
+}
+
+impl MoveReview {
+    /// The text `annotate` puts in the record, e.g. `?? blunder +4 -> -32760, best e5=X`.
+    pub fn comment(&self) -> String {
+        let mut comment = match self.judgement {
+            Some(j) => format!("{} {} ", j.symbol(), j.name()),
+            None => String::new(),
+        };
+        write!(comment, "{:+} -> {:+}", self.before, self.after).unwrap();
+        if let (Some(_), Some(best)) = (self.judgement, &self.best) {
+            write!(comment, ", best {}", best.mv).unwrap();
+        }
+        if let Some(line) = &self.missed_joy {
+            write!(comment, ", missed Joy: {}", line_text(line)).unwrap();
+        }
+        comment
+    }
+}
+
+fn line_text(line: &[Move]) -> String {
+    line.iter()
+        .map(|m| format!("{m}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The score of `game` for the side to move and the engine's best line there.
+/// Forced Joys the solver finds, for either side, win over the engine's opinion.
+fn position_score(game: &Game, limits: &Limits) -> (Evaluation, Option<CandidateMove>) {
+    if game.game_over {
+        // The player to move can only have lost, or drawn on a full board.
+        let score = if game.winner().is_some() {
+            -BEST_EVAL
+        } else {
+            0
+        };
+        return (score, None);
+    }
+    // A player who can't stop the opponent's Joy has lost, whatever wins it had in mind.
+    if let Some(pv) = solver::unstoppable_joy(game, FORCED_JOY_DEPTH) {
+        // Lost in as many moves as the winner needed one move earlier.
+        let score = -(BEST_EVAL - pv.len() as Evaluation - 1);
+        return (
+            score,
+            Some(CandidateMove {
+                mv: pv[0],
+                score,
+                pv,
+            }),
+        );
+    }
+    let limits = Limits {
+        candidates: 1,
+        ..*limits
+    };
+    let best = analyze(game, &limits).into_iter().next();
+    (best.as_ref().map_or(0, |b| b.score), best)
+}
+
+/// Replays `record` and judges every move by how much worse the position got for its player.
+pub fn review(
+    record: &GameRecord,
+    limits: &Limits,
+    thresholds: &Thresholds,
+) -> Result<Vec<MoveReview>, String> {
+    let mut game = record.start()?;
+    let mut positions = vec![game.clone()];
+    for (i, m) in record.moves.iter().enumerate() {
+        game.apply_move(*m)
+            .map_err(|e| format!("{e} {m} (move {})", i + 1))?;
+        positions.push(game.clone());
+    }
+    let scores: Vec<_> = positions
+        .iter()
+        .map(|game| position_score(game, limits))
+        .collect();
+    Ok(record
+        .moves
+        .iter()
+        .enumerate()
+        .map(|(i, &mv)| {
+            let (before, best) = &scores[i];
+            let after = -scores[i + 1].0;
+            let drop = *before as i32 - after as i32;
+            let next = &positions[i + 1];
+            let still_winning = next.winner().is_some()
+                || solver::unstoppable_joy(next, FORCED_JOY_DEPTH).is_some();
+            let missed_joy =
+                solver::forced_joy(&positions[i], FORCED_JOY_DEPTH).filter(|_| !still_winning);
+            MoveReview {
+                mv,
+                before: *before,
+                after,
+                best: best.clone().filter(|b| b.mv != mv),
+                judgement: thresholds.judge(drop),
+                missed_joy,
+            }
+        })
+        .collect())
+}
+
+/// `record` with the reviews as move comments, in place of the old ones.
+pub fn annotate(record: &GameRecord, reviews: &[MoveReview], annotator: &str) -> GameRecord {
+    let mut annotated = record.clone();
+    annotated.comments = reviews.iter().map(MoveReview::comment).collect();
+    annotated
+        .comments
+        .resize(annotated.moves.len(), String::new());
+    annotated.set_header("Annotator", annotator);
+    annotated
+}
+
+const STYLE: &str = "body { font-family: sans-serif; max-width: 60em; margin: auto; }
+table.board { border-collapse: collapse; margin: 0.5em 0; }
+table.board td { width: 1.6em; height: 1.6em; text-align: center; border: 1px solid #999;
+  font-family: monospace; font-weight: bold; }
+td.B { background: #cde; } td.X { background: #dec; } td.W { background: #fd8; }
+td.K { background: #caf; } td.J { background: #f88; }
+td.played { outline: 3px solid #c00; outline-offset: -3px; }
+td.best { outline: 3px dashed #080; outline-offset: -3px; }
+th { text-align: left; padding-right: 1em; }
+.blunder { color: #c00; } .mistake { color: #d60; } .inaccuracy { color: #990; }";
+
+/// A page showing the game with its reviews, with a diagram before every judged move and
+/// of the final position. It needs no other files.
+pub fn html_report(record: &GameRecord, reviews: &[MoveReview]) -> Result<String, String> {
+    let mut game = record.start()?;
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<title>Game review</title>\n<style>\n");
+    html.push_str(STYLE);
+    html.push_str("\n</style>\n</head>\n<body>\n<h1>Game review</h1>\n<table>\n");
+    for (key, value) in &record.headers {
+        writeln!(
+            html,
+            "<tr><th>{}</th><td>{}</td></tr>",
+            escape(key),
+            escape(value)
+        )
+        .unwrap();
+    }
+    html.push_str("</table>\n<h2>Summary</h2>\n<table>\n");
+    html.push_str("<tr><th></th><th>Blunders</th><th>Mistakes</th><th>Inaccuracies</th>");
+    html.push_str("<th>Missed Joys</th></tr>\n");
+    // A swap doesn't change the side to move, so the movers come from the replayed game.
+    let mut replayed = game.clone();
+    let mut movers = Vec::new();
+    for review in reviews {
+        movers.push(replayed.next_player);
+        replayed.apply_move(review.mv)?;
+    }
+    for (player, name) in ["Wealth", "Knowledge"].iter().enumerate() {
+        let own: Vec<&MoveReview> = reviews
+            .iter()
+            .zip(&movers)
+            .filter(|(_, &mover)| mover as usize == player)
+            .map(|(r, _)| r)
+            .collect();
+        let count = |j| own.iter().filter(|r| r.judgement == Some(j)).count();
+        writeln!(
+            html,
+            "<tr><th>{name}</th><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            count(Judgement::Blunder),
+            count(Judgement::Mistake),
+            count(Judgement::Inaccuracy),
+            own.iter().filter(|r| r.missed_joy.is_some()).count()
+        )
+        .unwrap();
+    }
+    html.push_str("</table>\n<h2>Moves</h2>\n");
+    for (i, review) in reviews.iter().enumerate() {
+        let class = review.judgement.map_or("", |j| j.name());
+        writeln!(
+            html,
+            "<p class=\"{class}\"><b>{}. {}</b> {}</p>",
+            i + 1,
+            review.mv,
+            escape(&review.comment())
+        )
+        .unwrap();
+        if review.judgement.is_some() || review.missed_joy.is_some() {
+            // A swap places no rune to mark.
+            let mut marks = Vec::new();
+            if !review.mv.is_swap() {
+                marks.push((review.mv.position, "played"));
+            }
+            if let Some(best) = review.best.as_ref().filter(|b| !b.mv.is_swap()) {
+                marks.push((best.mv.position, "best"));
+            }
+            html.push_str(&diagram(&game.board, &marks));
+        }
+        game.apply_move(review.mv)?;
+    }
+    html.push_str("<h2>Final position</h2>\n");
+    html.push_str(&diagram(&game.board, &[]));
+    html.push_str("</body>\n</html>\n");
+    Ok(html)
+}
+
+/// The board as a table, rows top down, with some fields marked by a CSS class.
+fn diagram(board: &Board, marks: &[(Position, &str)]) -> String {
+    let mut html = String::from("<table class=\"board\">\n");
+    for i in 0..board.size {
+        html.push_str("<tr>");
+        for j in 0..board.size {
+            let pos = Position(i, j);
+            let field = board.field_at(&pos);
+            let mut classes = Vec::new();
+            if field != Field::Empty {
+                classes.push(field.letter().to_string());
+            }
+            classes.extend(
+                marks
+                    .iter()
+                    .filter(|(p, _)| *p == pos)
+                    .map(|(_, class)| class.to_string()),
+            );
+            let letter = if field == Field::Empty {
+                ' '
+            } else {
+                field.letter()
+            };
+            write!(
+                html,
+                "<td class=\"{}\" title=\"{pos}\">{letter}</td>",
+                classes.join(" ")
+            )
+            .unwrap();
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
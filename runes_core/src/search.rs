@@ -0,0 +1,232 @@
+use std::{
+    cell::Cell,
+    fmt::Display,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use minimax::{Evaluation, Evaluator, Strategy, Winner, BEST_EVAL, WORST_EVAL};
+
+use crate::game::{Game, Move};
+
+/// What an AI found while choosing its last move.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchInfo {
+    /// Value of the move for the player who made it, on the scale of evaluations.
+    pub score: Evaluation,
+    /// Plies searched by alpha-beta or the solver.
+    pub depth: Option<u8>,
+    /// Random games played by tree search.
+    pub playouts: Option<u64>,
+    /// Positions looked at.
+    pub nodes: u64,
+    pub elapsed: Duration,
+    /// Expected continuation, starting with the chosen move.
+    pub pv: Vec<Move>,
+}
+
+impl SearchInfo {
+    /// A forced Joy found by the solver; it is worth just below `BEST_EVAL`.
+    pub fn forced_joy(line: Vec<Move>, elapsed: Duration) -> Self {
+        SearchInfo {
+            score: BEST_EVAL - line.len() as Evaluation,
+            depth: Some(line.len() as u8),
+            playouts: None,
+            nodes: line.len() as u64,
+            elapsed,
+            pv: line,
+        }
+    }
+}
+
+impl Display for SearchInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "score {:+}", self.score)?;
+        if let Some(depth) = self.depth {
+            write!(f, " depth {depth}")?;
+        }
+        if let Some(playouts) = self.playouts {
+            write!(f, " playouts {playouts}")?;
+        }
+        write!(
+            f,
+            " nodes {} time {}ms pv",
+            self.nodes,
+            self.elapsed.as_millis()
+        )?;
+        for m in &self.pv {
+            write!(f, " {m}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Alpha-beta Negamax over `Game::generate_moves`.
+///
+/// Unlike `minimax::Negamax` it doesn't shuffle the moves: among equally good moves the first
+/// generated one is chosen, so the same position always gets the same answer. It finds the
+/// same values at the same depth, see the test `alpha_beta_finds_the_values_of_negamax`.
+pub struct AlphaBeta<E: Evaluator<G = Game>> {
+    eval: E,
+    max_depth: u8,
+    root_value: Evaluation,
+    pv: Vec<Move>,
+    nodes: Cell<u64>,
+    stop: Arc<AtomicBool>,
+}
+
+impl<E: Evaluator<G = Game>> AlphaBeta<E> {
+    pub fn new(eval: E, depth: u8) -> Self {
+        Self {
+            eval,
+            max_depth: depth,
+            root_value: 0,
+            pv: Vec::new(),
+            nodes: Cell::new(0),
+            stop: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Setting the flag from another thread ends the running `choose_move`, which then returns
+    /// the best move among those it finished. The search clears the flag when it returns.
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    pub fn max_depth(&self) -> u8 {
+        self.max_depth
+    }
+
+    /// Positions visited by the last `choose_move` or `score_lines`.
+    pub fn nodes(&self) -> u64 {
+        self.nodes.get()
+    }
+
+    /// Value of the position given to the last `choose_move`, from the mover's point of view.
+    pub fn root_value(&self) -> Evaluation {
+        self.root_value
+    }
+
+    /// Exact value of each of `moves`, best first; equally good moves keep their order.
+    pub fn score_moves(&self, game: &Game, moves: Vec<Move>) -> Vec<(Move, Evaluation)> {
+        self.score_lines(game, moves)
+            .into_iter()
+            .map(|(value, line)| (line[0], value))
+            .collect()
+    }
+
+    /// Like `score_moves`, but with the expected continuation after each move.
+    /// Every line starts with the move it scores.
+    pub fn score_lines(&self, game: &Game, moves: Vec<Move>) -> Vec<(Evaluation, Vec<Move>)> {
+        let depth = self.max_depth.max(1);
+        self.nodes.set(0);
+        let mut scored: Vec<(Evaluation, Vec<Move>)> = moves
+            .into_iter()
+            .filter_map(|m| {
+                let mut next = game.clone();
+                next.apply_move(m).ok()?;
+                let mut line = vec![m];
+                let value = -self.negamax(&next, depth - 1, 1, WORST_EVAL, BEST_EVAL, &mut line);
+                Some((value, line))
+            })
+            .collect();
+        scored.sort_by_key(|(value, _)| std::cmp::Reverse(*value));
+        scored
+    }
+
+    /// Appends the best line found below `game` to `pv`.
+    fn negamax(
+        &self,
+        game: &Game,
+        depth: u8,
+        ply: u8,
+        mut alpha: Evaluation,
+        beta: Evaluation,
+        pv: &mut Vec<Move>,
+    ) -> Evaluation {
+        self.nodes.set(self.nodes.get() + 1);
+        if self.stopped() {
+            return 0;
+        }
+        if let Some(winner) = <Game as minimax::Game>::get_winner(game) {
+            return terminal_value(winner, ply);
+        }
+        if depth == 0 {
+            return self.eval.evaluate(game);
+        }
+        let mut best = WORST_EVAL;
+        let mut best_line = Vec::new();
+        for m in game.generate_moves() {
+            let mut next = game.clone();
+            if next.apply_move(m).is_err() {
+                continue;
+            }
+            let mut line = vec![m];
+            let value = -self.negamax(&next, depth - 1, ply + 1, -beta, -alpha, &mut line);
+            if best_line.is_empty() || value > best {
+                best = value;
+                best_line = line;
+            }
+            alpha = alpha.max(value);
+            if alpha >= beta {
+                break;
+            }
+        }
+        pv.extend(best_line);
+        best
+    }
+}
+
+/// Wins found sooner (and losses found later) are worth more than the same result deeper down.
+fn terminal_value(winner: Winner, ply: u8) -> Evaluation {
+    match winner {
+        Winner::PlayerJustMoved => WORST_EVAL + ply as Evaluation,
+        Winner::PlayerToMove => -WORST_EVAL - ply as Evaluation,
+        Winner::Draw => 0,
+    }
+}
+
+impl<E: Evaluator<G = Game>> Strategy<Game> for AlphaBeta<E> {
+    fn choose_move(&mut self, game: &Game) -> Option<Move> {
+        if self.max_depth == 0 || game.game_over {
+            return None;
+        }
+        self.nodes.set(0);
+        let mut best = WORST_EVAL;
+        let mut best_line: Vec<Move> = Vec::new();
+        for m in game.generate_moves() {
+            let mut next = game.clone();
+            if next.apply_move(m).is_err() {
+                continue;
+            }
+            let mut line = vec![m];
+            let value = -self.negamax(&next, self.max_depth - 1, 1, WORST_EVAL, -best, &mut line);
+            if self.stopped() && !best_line.is_empty() {
+                break;
+            }
+            if best_line.is_empty() || value > best {
+                best = value;
+                best_line = line;
+            }
+        }
+        self.stop.store(false, Ordering::Relaxed);
+        self.root_value = best;
+        self.pv = best_line;
+        self.pv.first().copied()
+    }
+
+    fn set_max_depth(&mut self, depth: u8) {
+        self.max_depth = depth;
+    }
+
+    fn principal_variation(&self) -> Vec<Move> {
+        self.pv.clone()
+    }
+}
@@ -0,0 +1,134 @@
+use crate::game::{Game, Move, Player, PLAYER_SYMBOLS};
+
+/// A player that couldn't make a move and lost because of it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Forfeit {
+    pub player: u8,
+    pub reason: String,
+}
+
+/// Drives a game between two players until it is over.
+pub struct Session {
+    pub players: [Box<dyn Player>; 2],
+    pub game: Game,
+    /// The position before the first move.
+    pub start: Game,
+    /// The moves played since `start`.
+    pub moves: Vec<Move>,
+    /// Lets each player think on during the opponent's turn.
+    pub ponder: bool,
+    /// Set when the game ended because a player failed to move.
+    pub forfeit: Option<Forfeit>,
+    /// The players changed sides under the pie rule, `players[0]` is the one who came second.
+    pub swapped: bool,
+}
+
+impl Session {
+    pub fn new(player1: Box<dyn Player>, player2: Box<dyn Player>, board_size: usize) -> Self {
+        Session::with_game(player1, player2, Game::new(board_size))
+    }
+
+    /// Like `new`, but the second player may take over the first player's side after the
+    /// first move.
+    pub fn with_pie_rule(
+        player1: Box<dyn Player>,
+        player2: Box<dyn Player>,
+        board_size: usize,
+    ) -> Self {
+        let mut game = Game::new(board_size);
+        game.pie_rule = true;
+        Session::with_game(player1, player2, game)
+    }
+
+    /// Starts from an already set up `game`, e.g. one with a few opening moves played.
+    pub fn with_game(player1: Box<dyn Player>, player2: Box<dyn Player>, game: Game) -> Self {
+        let mut session = Self {
+            players: [player1, player2],
+            start: game.clone(),
+            game,
+            moves: Vec::new(),
+            ponder: false,
+            forfeit: None,
+            swapped: false,
+        };
+        session.assign_symbols();
+        session
+    }
+
+    /// Gives `players[i]` the rune of side `i`.
+    fn assign_symbols(&mut self) {
+        for (player, symbol) in self.players.iter_mut().zip(PLAYER_SYMBOLS) {
+            player.set_symbol(symbol);
+        }
+    }
+
+    /// The players change sides, so that `players[i]` still plays side `i`.
+    fn swap_players(&mut self) {
+        self.players.swap(0, 1);
+        self.assign_symbols();
+        self.swapped = !self.swapped;
+    }
+
+    /// Calls `callback` before every move.
+    pub fn start_loop(&mut self, mut callback: impl FnMut(&Self)) {
+        while !self.game.game_over && self.forfeit.is_none() {
+            callback(self);
+            let next_player = self.game.next_player;
+            let player_move =
+                match self.players[next_player as usize].try_make_move(self.game.clone()) {
+                    Ok(m) => m,
+                    Err(reason) => {
+                        self.forfeit = Some(Forfeit {
+                            player: next_player,
+                            reason,
+                        });
+                        break;
+                    }
+                };
+            match self.game.apply_move(player_move) {
+                Ok(_) if player_move.is_swap() => {
+                    self.moves.push(player_move);
+                    self.swap_players();
+                }
+                Ok(_) => {
+                    self.moves.push(player_move);
+                    if self.ponder && !self.game.game_over {
+                        self.players[next_player as usize].ponder(self.game.clone())
+                    }
+                }
+                Err(s) => {
+                    self.forfeit = Some(Forfeit {
+                        player: next_player,
+                        reason: format!("{s} {player_move}"),
+                    });
+                }
+            }
+        }
+        for player in &self.players {
+            player.stop_pondering();
+        }
+    }
+
+    /// Index of the player who won, also by forfeit.
+    pub fn winner_index(&self) -> Option<u8> {
+        match &self.forfeit {
+            Some(forfeit) => Some(1 - forfeit.player),
+            None => self.game.winner(),
+        }
+    }
+
+    pub fn winner(&self) -> Option<String> {
+        self.winner_index()
+            .map(|idx| self.players[idx as usize].name())
+    }
+
+    pub fn reset(&mut self) {
+        if self.swapped {
+            self.swap_players();
+        }
+        self.game.reset();
+        self.start = self.game.clone();
+        self.moves.clear();
+        self.forfeit = None;
+    }
+}
@@ -0,0 +1,185 @@
+use std::fmt::{Debug, Display};
+
+use rand::{seq::IteratorRandom, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    ai_player::FORCED_JOY_DEPTH,
+    board::{Board, Field, Position},
+    game::{Game, PLAYER_SYMBOLS},
+    record::{parse_start, start_text},
+    solver,
+};
+
+/// A starting position with a name, from a setup file.
+///
+/// Setup files hold one setup per block of `Key: value` lines, blocks are separated by an
+/// empty line. `Start` is written like the header of a `GameRecord`, `Rules: pie` turns on
+/// the pie rule:
+///
+/// ```text
+/// Name: Two Births
+/// Start: ......./......./..B..../......./....B../......./....... W
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct Setup {
+    pub name: String,
+    pub game: Game,
+}
+
+/// Is `game` a position to start from: reachable by legal play, and neither can the player
+/// to move force a Joy nor is the opponent's Joy already unstoppable?
+pub fn check_undecided(game: &Game) -> Result<(), String> {
+    game.validate()?;
+    if game.game_over {
+        return Err("The game is already over".to_string());
+    }
+    let side = |player: u8| ["Wealth", "Knowledge"][player as usize];
+    if solver::forced_joy(game, FORCED_JOY_DEPTH).is_some() {
+        return Err(format!("{} can force a Joy", side(game.next_player)));
+    }
+    if solver::unstoppable_joy(game, FORCED_JOY_DEPTH).is_some() {
+        return Err(format!("{} can force a Joy", side(1 - game.next_player)));
+    }
+    Ok(())
+}
+
+/// Random boards `scatter` tries before it gives up.
+const SCATTER_ATTEMPTS: usize = 1_000;
+
+/// An empty board with `count` Births and Gifts placed at random, Wealth to move. Each rune is
+/// placed where the rules allow it and positions that are already decided are thrown away,
+/// so the same seed always gives the same position.
+pub fn scatter(size: usize, count: usize, seed: u64) -> Result<Game, String> {
+    if count > size * size / 2 {
+        return Err(format!("Too many runes for a {size}x{size} board"));
+    }
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    for _ in 0..SCATTER_ATTEMPTS {
+        let mut game = Game::from_board(Board::new(size), 0);
+        for _ in 0..count {
+            let pos = (0..size)
+                .flat_map(|i| (0..size).map(move |j| Position(i, j)))
+                .filter(|pos| game.board.is_empty(pos))
+                .choose(&mut rng)
+                .unwrap();
+            // Birth on a free spot, Gift next to other runes.
+            let symbol = game.valid_symbols_at(&pos)[0];
+            game.board.change(pos, symbol);
+        }
+        let game = Game::from_board(game.board, 0);
+        if check_undecided(&game).is_ok() {
+            return Ok(game);
+        }
+    }
+    Err(format!(
+        "No undecided position with {count} runes on {size}x{size} in {SCATTER_ATTEMPTS} tries"
+    ))
+}
+
+/// Handicap runes fill these corners first, top or bottom and left or right.
+const HANDICAP_CORNERS: [(bool, bool); 4] =
+    [(false, false), (true, true), (false, true), (true, false)];
+
+/// `Game::new` with `runes` runes of `side` placed for the weaker player, at most four, one
+/// near each corner. Each one comes with the Birth and Gifts it needs, and the stronger
+/// side moves first. Small boards have no room for some of them.
+pub fn handicap(size: usize, runes: usize, side: u8) -> Result<Game, String> {
+    if runes > HANDICAP_CORNERS.len() {
+        return Err(format!("At most {} handicap runes", HANDICAP_CORNERS.len()));
+    }
+    let no_room = || format!("No room for {runes} handicap runes on {size}x{size}");
+    // A corner takes the two rows and columns next to the edge.
+    if runes > 0 && size < 5 {
+        return Err(no_room());
+    }
+    let symbol = PLAYER_SYMBOLS[side as usize];
+    let mut board = Game::new(size).board;
+    for &(bottom, right) in &HANDICAP_CORNERS[..runes] {
+        // The Birth one field off the corner, the rune and two Gifts towards the center.
+        let row = if bottom { size - 2 } else { 1 };
+        let col = if right { size - 2 } else { 1 };
+        let inward = |x: usize, far: bool| if far { x - 1 } else { x + 1 };
+        for (pos, field) in [
+            (Position(row, col), Field::Birth),
+            (Position(row, inward(col, right)), Field::Gift),
+            (Position(inward(row, bottom), col), symbol),
+            (
+                Position(inward(row, bottom), inward(col, right)),
+                Field::Gift,
+            ),
+        ] {
+            if !board.is_empty(&pos) {
+                return Err(no_room());
+            }
+            board.change(pos, field);
+        }
+    }
+    let game = Game::from_board(board, 1 - side);
+    let joy_square = (0..size)
+        .flat_map(|i| (0..size).map(move |j| Position(i, j)))
+        .any(|pos| PLAYER_SYMBOLS.iter().any(|&s| game.is_joy_square(&pos, s)));
+    if joy_square {
+        return Err(no_room());
+    }
+    check_undecided(&game).map_err(|e| format!("{}: {e}", no_room()))?;
+    Ok(game)
+}
+
+/// Reads a setup file, every setup must be a position to start from.
+pub fn parse_setups(text: &str) -> Result<Vec<Setup>, String> {
+    text.split("\n\n")
+        .filter(|block| !block.trim().is_empty())
+        .enumerate()
+        .map(|(i, block)| parse_setup(block).map_err(|e| format!("Setup {}: {e}", i + 1)))
+        .collect()
+}
+
+fn parse_setup(block: &str) -> Result<Setup, String> {
+    let mut name = None;
+    let mut game = None;
+    let mut pie_rule = false;
+    for line in block.lines().filter(|l| !l.trim().is_empty()) {
+        let (key, value) = line
+            .split_once(':')
+            .ok_or(format!("Expected `Key: value`, got `{line}`"))?;
+        let value = value.trim();
+        match key.trim() {
+            "Name" => name = Some(value.to_string()),
+            "Start" => game = Some(parse_start(value)?),
+            "Rules" => pie_rule = value == "pie",
+            _ => (),
+        }
+    }
+    let name = name.ok_or("Missing Name")?;
+    let mut game = game.ok_or(format!("Missing Start in `{name}`"))?;
+    game.pie_rule = pie_rule;
+    check_undecided(&game).map_err(|e| format!("`{name}`: {e}"))?;
+    Ok(Setup { name, game })
+}
+
+/// Writes setups the way `parse_setups` reads them.
+pub fn write_setups(setups: &[Setup]) -> String {
+    setups
+        .iter()
+        .map(Setup::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl Display for Setup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Name: {}", self.name)?;
+        writeln!(f, "Start: {}", start_text(&self.game))?;
+        if self.game.pie_rule {
+            writeln!(f, "Rules: pie")?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for Setup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Setup({:?}, {})", self.name, start_text(&self.game))
+    }
+}
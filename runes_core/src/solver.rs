@@ -0,0 +1,187 @@
+use crate::{
+    board::{Field, Position},
+    game::{Game, Move},
+};
+
+/// Threat-space search: can the side to move force a Joy within `max_moves` of its own moves?
+///
+/// Only moves that create a Joy square are tried for the attacker, while every move that
+/// removes all of the attacker's Joy squares is tried for the defender. The returned line
+/// alternates attacker and defender moves and ends with the winning Joy.
+pub fn forced_joy(game: &Game, max_moves: usize) -> Option<Vec<Move>> {
+    if game.game_over || max_moves == 0 {
+        return None;
+    }
+    attack(game, max_moves)
+}
+
+/// Every first move with which the side to move forces a Joy within `max_moves` of its own
+/// moves. Unlike `forced_joy` it tries all legal moves, also the quiet ones that make no Joy
+/// square and leave the defender free to answer anything, so it is much slower.
+pub fn winning_moves(game: &Game, max_moves: usize) -> Vec<Move> {
+    if game.game_over || max_moves == 0 {
+        return Vec::new();
+    }
+    let joys = game.joy_squares(game.next_player_symbol());
+    if !joys.is_empty() {
+        return joys.into_iter().map(|p| Move::new(p, Field::Joy)).collect();
+    }
+    if max_moves < 2 {
+        return Vec::new();
+    }
+    let threats = threat_moves(game);
+    game.legal_moves()
+        .into_iter()
+        .filter(|m| !m.is_swap())
+        .filter(
+            |m| match threats.iter().find(|(threat, _, _)| threat == m) {
+                Some((_, next, _)) => defend(next, max_moves - 1).is_some(),
+                None => quiet_move_wins(game, *m, max_moves - 1),
+            },
+        )
+        .collect()
+}
+
+/// Does the side to move still force a Joy within `moves_left` of its own moves after `m`,
+/// whatever the defender answers?
+fn quiet_move_wins(game: &Game, m: Move, moves_left: usize) -> bool {
+    let mut next = game.clone();
+    if next.apply_move(m).is_err() || next.game_over {
+        return false;
+    }
+    next.legal_moves()
+        .into_iter()
+        .filter(|reply| !reply.is_swap())
+        .all(|reply| {
+            let mut after = next.clone();
+            after.apply_move(reply).is_ok() && attack(&after, moves_left).is_some()
+        })
+}
+
+/// Can the opponent of the side to move force a Joy within `max_moves` of its own moves,
+/// whatever the side to move does? Returns the longest defence, ending with the Joy.
+///
+/// Only an attack under way counts: without a Joy square of the opponent on the board it is
+/// `None`, even when the opponent's next move would start an attack nothing stops.
+pub fn unstoppable_joy(game: &Game, max_moves: usize) -> Option<Vec<Move>> {
+    // Otherwise `defend` would take any move of the side to move for a failed block.
+    if max_moves == 0 || game.joy_squares(game.opponent_symbol()).is_empty() {
+        return None;
+    }
+    defend(game, max_moves)
+}
+
+fn attack(game: &Game, moves_left: usize) -> Option<Vec<Move>> {
+    if game.game_over {
+        return None;
+    }
+    if let Some(&pos) = game.joy_squares(game.next_player_symbol()).first() {
+        return Some(vec![Move::new(pos, Field::Joy)]);
+    }
+    if moves_left < 2 {
+        return None;
+    }
+    let mut threats = threat_moves(game);
+    // Try double threats first, they are the ones that usually can't be stopped.
+    threats.sort_by_key(|(_, _, count)| std::cmp::Reverse(*count));
+    threats.into_iter().find_map(|(m, next, _)| {
+        let mut line = defend(&next, moves_left - 1)?;
+        line.insert(0, m);
+        Some(line)
+    })
+}
+
+fn defend(game: &Game, moves_left: usize) -> Option<Vec<Move>> {
+    if game.game_over || !game.joy_squares(game.next_player_symbol()).is_empty() {
+        return None;
+    }
+    let threats = game.joy_squares(game.opponent_symbol());
+    let blocks = blocking_moves(game, &threats);
+    if blocks.is_empty() {
+        // Whatever the defender plays, at least one Joy square survives.
+        let reply = *game.generate_moves().first()?;
+        let mut next = game.clone();
+        next.apply_move(reply).ok()?;
+        let mut line = attack(&next, moves_left)?;
+        line.insert(0, reply);
+        return Some(line);
+    }
+    let mut longest: Option<Vec<Move>> = None;
+    for reply in blocks {
+        let mut next = game.clone();
+        if next.apply_move(reply).is_err() {
+            continue;
+        }
+        let mut line = attack(&next, moves_left)?;
+        line.insert(0, reply);
+        if longest.as_ref().is_none_or(|l| l.len() < line.len()) {
+            longest = Some(line);
+        }
+    }
+    longest
+}
+
+/// Attacker moves that create at least one Joy square without leaving one to the defender.
+fn threat_moves(game: &Game) -> Vec<(Move, Game, usize)> {
+    let attacker = game.next_player_symbol();
+    let defender = game.opponent_symbol();
+    let defender_squares = game.joy_squares(defender);
+    let size = game.board.size;
+    let mut moves = Vec::new();
+    for i in 0..size {
+        for j in 0..size {
+            let pos = Position(i, j);
+            if !game.board.is_empty(&pos) || !has_stone_within(game, &pos, 2) {
+                continue;
+            }
+            for symbol in game.valid_symbols_at(&pos) {
+                if symbol == Field::Joy {
+                    continue;
+                }
+                let mut next = game.clone();
+                if next.apply_move(Move::new(pos, symbol)).is_err() {
+                    continue;
+                }
+                // The attacker had no Joy square before, so new ones must touch this move.
+                let count = around(size, &pos, 1)
+                    .filter(|p| next.is_joy_square(p, attacker))
+                    .count();
+                let defender_left = defender_squares
+                    .iter()
+                    .copied()
+                    .chain(around(size, &pos, 1))
+                    .any(|p| next.is_joy_square(&p, defender));
+                if count > 0 && !defender_left {
+                    moves.push((Move::new(pos, symbol), next, count));
+                }
+            }
+        }
+    }
+    moves
+}
+
+/// Defender moves on fields that are next to (or on) every one of the `threats`.
+fn blocking_moves(game: &Game, threats: &[Position]) -> Vec<Move> {
+    let Some(first) = threats.first() else {
+        return Vec::new();
+    };
+    around(game.board.size, first, 1)
+        .filter(|p| game.board.is_empty(p) && threats.iter().all(|t| t.near(p)))
+        .flat_map(|p| {
+            game.valid_symbols_at(&p)
+                .into_iter()
+                .map(move |symbol| Move::new(p, symbol))
+        })
+        .collect()
+}
+
+fn has_stone_within(game: &Game, pos: &Position, distance: usize) -> bool {
+    around(game.board.size, pos, distance).any(|p| !game.board.is_empty(&p))
+}
+
+/// All positions at most `distance` rows and columns away from `pos`, including `pos` itself.
+fn around(size: usize, pos: &Position, distance: usize) -> impl Iterator<Item = Position> {
+    let rows = pos.0.saturating_sub(distance)..=(pos.0 + distance).min(size - 1);
+    let cols = pos.1.saturating_sub(distance)..=(pos.1 + distance).min(size - 1);
+    rows.flat_map(move |i| cols.clone().map(move |j| Position(i, j)))
+}
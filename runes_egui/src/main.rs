@@ -1,3 +1,4 @@
+mod trainer;
 mod wiregrid;
 use std::{
     sync::{Arc, Mutex},
@@ -6,13 +7,17 @@ use std::{
 
 use runes_core::{
     ai_player::{AiPlayerMonte, Level},
+    analysis::{analyze, CandidateMove, Limits},
     board::Position,
     game::{Game, Move, Player},
+    search::SearchInfo,
+    setup::handicap,
 };
+use trainer::{Outcome, Trainer};
 use wiregrid::WireGrid;
 
 use eframe::egui;
-use egui::{ComboBox, InnerResponse, TextureId, Ui};
+use egui::{Area, ComboBox, Frame, InnerResponse, Order, Pos2, Response, TextureId, Ui};
 use egui_extras::RetainedImage;
 
 fn main() {
@@ -37,15 +42,41 @@ pub struct TextureIds {
     pub stone2: TextureId,
 }
 
+/// A move of the AI thread and what its search found.
+type AiReply = (Move, Option<SearchInfo>);
+/// Hints with the position they were found for.
+type Hints = (Game, Vec<CandidateMove>);
+
 struct MyEguiApp {
-    size: usize,
     _store: Vec<RetainedImage>,
     pub images: TextureIds,
     game: Game,
     ai_level: Level,
-    ai_move: Arc<Mutex<Option<Move>>>,
+    /// New games are played with the pie rule.
+    pie_rule: bool,
+    /// Runes of the human player on the board when a new game starts.
+    handicap: usize,
+    /// The side of the human player, it changes when they swap.
+    human: u8,
+    ai_info: Option<SearchInfo>,
+    /// Kept between moves so it can ponder while the human thinks.
+    ai: Arc<Mutex<AiPlayerMonte>>,
+    ai_move: Arc<Mutex<Option<AiReply>>>,
+    /// The last hints, shown only in the position they are for.
+    hints: Arc<Mutex<Option<Hints>>>,
+    /// Set while solving puzzles instead of playing.
+    trainer: Option<Trainer>,
+    puzzle_path: String,
+    /// Why the puzzles couldn't be loaded.
+    puzzle_error: String,
+    /// The field and screen position of the open menu of runes, opened with a right click
+    /// or a long press.
+    symbol_menu: Option<(Position, Pos2)>,
 }
 
+/// Seconds to hold a press before the menu of runes opens, longer than a click can take.
+const LONG_PRESS: f64 = 0.6;
+
 impl MyEguiApp {
     fn new(cc: &eframe::CreationContext<'_>) -> Self {
         // Customize egui here with cc.egui_ctx.set_fonts and cc.egui_ctx.set_visuals.
@@ -74,11 +105,17 @@ impl MyEguiApp {
 
         let ai_level = Level::Medium;
         let mut game = Game::new(13);
-        let ai_first = AiPlayerMonte::new(ai_level).make_move(game.clone());
-        game.apply_move(ai_first).unwrap();
+        let ai = new_ai(ai_level, &mut game);
+        let ai_info = ai.lock().unwrap().search_info();
+        // A puzzle file on the command line opens the puzzle trainer.
+        let puzzle_path = std::env::args().nth(1);
+        let (trainer, puzzle_error) = match puzzle_path.as_deref().map(Trainer::load) {
+            Some(Ok(trainer)) => (Some(trainer), String::new()),
+            Some(Err(e)) => (None, e),
+            None => (None, String::new()),
+        };
 
         Self {
-            size: game.board.size,
             images: TextureIds {
                 birth: store[0].texture_id(&cc.egui_ctx),
                 gift: store[1].texture_id(&cc.egui_ctx),
@@ -92,21 +129,44 @@ impl MyEguiApp {
             _store: store,
             game,
             ai_level,
+            pie_rule: false,
+            handicap: 0,
+            human: 1,
+            ai_info,
+            ai,
             ai_move: Arc::new(Mutex::new(None)),
+            hints: Arc::new(Mutex::new(None)),
+            trainer,
+            puzzle_path: puzzle_path.unwrap_or_else(|| "puzzles.txt".to_string()),
+            puzzle_error,
+            symbol_menu: None,
         }
     }
 
     fn main_ui(&mut self, ui: &mut Ui) -> InnerResponse<()> {
-        if let Some(ai_move) = self.ai_move.lock().unwrap().take() {
+        if self.trainer.is_some() {
+            return self.puzzle_ui(ui);
+        }
+        if let Some((ai_move, info)) = self.ai_move.lock().unwrap().take() {
             self.game.apply_move(ai_move).unwrap();
+            self.ai_info = info;
         }
+        // Hints that come in after a move are for the position before it.
+        let hints = match &*self.hints.lock().unwrap() {
+            Some((game, found)) if *game == self.game => found.clone(),
+            _ => Vec::new(),
+        };
 
         ui.vertical(|ui| {
             ui.horizontal(|ui| {
                 if ui.button("New Game").clicked() {
-                    self.game = Game::new(self.game.board.size);
-                    let ai_first = AiPlayerMonte::new(self.ai_level).make_move(self.game.clone());
-                    self.game.apply_move(ai_first).unwrap();
+                    *self.hints.lock().unwrap() = None;
+                    // The AI gives no handicap it could take back with a swap.
+                    self.game = handicap(self.game.board.size, self.handicap, 1).unwrap();
+                    self.game.pie_rule = self.pie_rule && self.handicap == 0;
+                    self.human = 1;
+                    self.ai = new_ai(self.ai_level, &mut self.game);
+                    self.ai_info = self.ai.lock().unwrap().search_info();
                 };
                 ComboBox::from_label("AI Level")
                     .selected_text(format!("{:?}", self.ai_level))
@@ -116,36 +176,215 @@ impl MyEguiApp {
                         ui.selectable_value(&mut self.ai_level, Level::Hard, "Hard");
                         ui.selectable_value(&mut self.ai_level, Level::VeryHard, "VeryHard");
                     });
+                ComboBox::from_label("Handicap")
+                    .selected_text(self.handicap.to_string())
+                    .show_ui(ui, |ui| {
+                        for runes in 0..=4 {
+                            ui.selectable_value(&mut self.handicap, runes, runes.to_string());
+                        }
+                    });
+                ui.checkbox(&mut self.pie_rule, "Pie rule");
+                if self.game.can_swap()
+                    && self.game.next_player == self.human
+                    && ui.button("Swap").clicked()
+                {
+                    self.game.apply_move(Move::SWAP).unwrap();
+                    self.human = 1 - self.human;
+                    self.start_ai_move(ui.ctx().clone());
+                }
+                if ui.button("Hint").clicked()
+                    && !self.game.game_over
+                    && self.game.next_player == self.human
+                {
+                    let hints = self.hints.clone();
+                    let hint_board = self.game.clone();
+                    let ctx = ui.ctx().clone();
+                    thread::spawn(move || {
+                        let found = analyze(&hint_board, &Limits::default());
+                        *hints.lock().unwrap() = Some((hint_board, found));
+                        ctx.request_repaint();
+                    });
+                }
+                for c in &hints {
+                    ui.label(format!("{} ({:+})", c.mv, c.score));
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut self.puzzle_path);
+                if ui.button("Puzzles").clicked() {
+                    match Trainer::load(&self.puzzle_path) {
+                        Ok(trainer) => {
+                            self.trainer = Some(trainer);
+                            self.puzzle_error.clear();
+                        }
+                        Err(e) => self.puzzle_error = e,
+                    }
+                }
+                ui.label(&self.puzzle_error);
             });
+            if let Some(info) = &self.ai_info {
+                ui.label(format!("AI: {info}"));
+            }
             let grid_response = ui.add(WireGrid {
                 board: self.game.board.clone(),
                 textures: self.images,
+                hints: hints.iter().map(|c| c.mv.position).collect(),
             });
-            if !self.game.game_over && self.game.next_player == 1 && grid_response.clicked() {
-                let clicked = WireGrid::get_clicked_cell(self.size, &grid_response);
-                match self
-                    .game
-                    .apply_best_move_at(&Position(clicked.0, clicked.1))
-                {
-                    Ok(_) if !self.game.game_over => {
-                        let ai_move = self.ai_move.clone();
-                        let ai_board = self.game.clone();
-                        let ai_level = self.ai_level;
-                        let ctx = ui.ctx().clone();
-                        thread::spawn(move || {
-                            let ai = AiPlayerMonte::new(ai_level);
-                            let made = ai.make_move(ai_board);
-                            let mut x = ai_move.lock().unwrap();
-                            *x = Some(made);
-                            ctx.request_repaint();
-                        });
-                    }
-                    Ok(_) => (),
-                    Err(_) => (), //Messagebox
-                }
-            };
+            let our_turn = !self.game.game_over && self.game.next_player == self.human;
+            if let Some(m) = pick_move(
+                ui,
+                &grid_response,
+                &self.game,
+                our_turn,
+                &mut self.symbol_menu,
+            ) {
+                self.play(m, ui.ctx().clone());
+            }
         })
     }
+
+    /// Plays the human's move and lets the AI answer.
+    fn play(&mut self, m: Move, ctx: egui::Context) {
+        if self.game.apply_move(m).is_ok() && !self.game.game_over {
+            *self.hints.lock().unwrap() = None;
+            self.start_ai_move(ctx);
+        }
+    }
+
+    /// Lets the AI move on a thread, it ponders on once the move is made.
+    fn start_ai_move(&mut self, ctx: egui::Context) {
+        let ai_move = self.ai_move.clone();
+        let mut ai_board = self.game.clone();
+        if self.ai.lock().unwrap().level != self.ai_level {
+            self.ai.lock().unwrap().stop_pondering();
+            self.ai = Arc::new(Mutex::new(AiPlayerMonte::new(self.ai_level)));
+        }
+        let ai = self.ai.clone();
+        thread::spawn(move || {
+            let ai = ai.lock().unwrap();
+            let made = ai.make_move(ai_board.clone());
+            *ai_move.lock().unwrap() = Some((made, ai.search_info()));
+            ctx.request_repaint();
+            if ai_board.apply_move(made).is_ok() && !ai_board.game_over {
+                ai.ponder(ai_board);
+            }
+        });
+    }
+}
+
+impl MyEguiApp {
+    fn puzzle_ui(&mut self, ui: &mut Ui) -> InnerResponse<()> {
+        let trainer = self.trainer.as_mut().unwrap();
+        let mut close = false;
+        let response = ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                close = ui.button("Back to Game").clicked();
+                let (index, count) = (trainer.index, trainer.puzzles.len());
+                if ui.button("Previous").clicked() && index > 0 {
+                    trainer.select(index - 1);
+                }
+                if ui.button("Next").clicked() && index + 1 < count {
+                    trainer.select(index + 1);
+                }
+                if ui.button("Retry").clicked() {
+                    trainer.select(index);
+                }
+                let progress = &trainer.progress;
+                let mark = match progress.get(trainer.puzzle()) {
+                    Some(Outcome::Solved) => " (solved)",
+                    Some(Outcome::Failed) => " (failed)",
+                    None => "",
+                };
+                ui.label(format!(
+                    "Puzzle {}/{count}{mark}, {} solved, {} failed",
+                    trainer.index + 1,
+                    progress.count(&trainer.puzzles, Outcome::Solved),
+                    progress.count(&trainer.puzzles, Outcome::Failed),
+                ));
+            });
+            ui.label(&trainer.message);
+            let grid_response = ui.add(WireGrid {
+                board: trainer.game.board.clone(),
+                textures: self.images,
+                hints: Vec::new(),
+            });
+            let can_move = trainer.outcome.is_none();
+            let menu = &mut self.symbol_menu;
+            if let Some(m) = pick_move(ui, &grid_response, &trainer.game, can_move, menu) {
+                trainer.play(m);
+            }
+        });
+        if close {
+            self.trainer = None;
+            self.symbol_menu = None;
+        }
+        response
+    }
+}
+
+/// The move chosen on the board `grid` of `game`. A click plays the best rune for the
+/// field, a right click or a long press opens `menu` to choose one.
+fn pick_move(
+    ui: &Ui,
+    grid: &Response,
+    game: &Game,
+    can_move: bool,
+    menu: &mut Option<(Position, Pos2)>,
+) -> Option<Move> {
+    let size = game.board.size;
+    // A click elsewhere only closes the menu.
+    let menu_was_open = menu.is_some();
+    if let Some((position, at)) = *menu {
+        let mut chosen = None;
+        let area = Area::new("symbol_menu")
+            .order(Order::Foreground)
+            .fixed_pos(at)
+            .show(ui.ctx(), |ui| {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    for symbol in game.valid_symbols_at(&position) {
+                        let label = format!("{} ({})", symbol.name(), symbol.letter());
+                        if ui.button(label).clicked() {
+                            chosen = Some(symbol);
+                        }
+                    }
+                });
+            });
+        if let Some(symbol) = chosen {
+            *menu = None;
+            return Some(Move::new(position, symbol));
+        } else if area.response.clicked_elsewhere() || !can_move {
+            *menu = None;
+        }
+    }
+    let held = grid.is_pointer_button_down_on();
+    if held {
+        // Check the press again in a moment, as no event comes while it is held.
+        ui.ctx().request_repaint();
+    }
+    let long_press = held
+        && ui.input(|i| {
+            i.pointer
+                .press_start_time()
+                .is_some_and(|start| i.time - start >= LONG_PRESS)
+        });
+    if can_move && (grid.secondary_clicked() || long_press) {
+        let clicked = WireGrid::get_clicked_cell(size, grid);
+        let at = grid.interact_pointer_pos().unwrap();
+        *menu = Some((Position(clicked.0, clicked.1), at));
+    } else if can_move && !menu_was_open && grid.clicked() {
+        let clicked = WireGrid::get_clicked_cell(size, grid);
+        let position = Position(clicked.0, clicked.1);
+        return Some(Move::new(position, game.best_symbol_at(&position)));
+    }
+    None
+}
+
+/// An AI that has made the opening move in `game` and ponders the reply.
+fn new_ai(level: Level, game: &mut Game) -> Arc<Mutex<AiPlayerMonte>> {
+    let ai = AiPlayerMonte::new(level);
+    game.apply_move(ai.make_move(game.clone())).unwrap();
+    ai.ponder(game.clone());
+    Arc::new(Mutex::new(ai))
 }
 
 impl eframe::App for MyEguiApp {
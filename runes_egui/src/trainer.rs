@@ -0,0 +1,173 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use runes_core::{
+    game::{Game, Move},
+    puzzle::{parse_puzzles, Puzzle},
+    record::start_text,
+};
+
+/// Where a puzzle stands for the player.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Solved,
+    Failed,
+}
+
+/// Solved and failed puzzles, kept in a text file between runs. One line per puzzle,
+/// `solved` or `failed` and then its start position.
+pub struct Progress {
+    path: PathBuf,
+    outcomes: BTreeMap<String, Outcome>,
+}
+
+impl Progress {
+    pub fn load() -> Self {
+        let path = progress_path();
+        let outcomes = fs::read_to_string(&path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let (outcome, key) = line.split_once(' ')?;
+                let outcome = match outcome {
+                    "solved" => Outcome::Solved,
+                    "failed" => Outcome::Failed,
+                    _ => return None,
+                };
+                Some((key.to_string(), outcome))
+            })
+            .collect();
+        Progress { path, outcomes }
+    }
+
+    pub fn get(&self, puzzle: &Puzzle) -> Option<Outcome> {
+        self.outcomes.get(&key(puzzle)).copied()
+    }
+
+    /// Remembers the latest outcome of `puzzle` and writes the file.
+    pub fn set(&mut self, puzzle: &Puzzle, outcome: Outcome) {
+        self.outcomes.insert(key(puzzle), outcome);
+        let text: String = self
+            .outcomes
+            .iter()
+            .map(|(key, outcome)| match outcome {
+                Outcome::Solved => format!("solved {key}\n"),
+                Outcome::Failed => format!("failed {key}\n"),
+            })
+            .collect();
+        if let Some(dir) = self.path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        if let Err(e) = fs::write(&self.path, text) {
+            eprintln!("Can't write {}: {e}", self.path.display());
+        }
+    }
+
+    pub fn count(&self, puzzles: &[Puzzle], outcome: Outcome) -> usize {
+        puzzles
+            .iter()
+            .filter(|p| self.get(p) == Some(outcome))
+            .count()
+    }
+}
+
+fn key(puzzle: &Puzzle) -> String {
+    start_text(&puzzle.start)
+}
+
+/// The app's data directory, or the working directory if there is none.
+fn progress_path() -> PathBuf {
+    let dir = if let Some(data) = std::env::var_os("XDG_DATA_HOME") {
+        PathBuf::from(data).join("runes")
+    } else if let Some(appdata) = std::env::var_os("APPDATA") {
+        PathBuf::from(appdata).join("runes")
+    } else if let Some(home) = std::env::var_os("HOME") {
+        PathBuf::from(home).join(".local/share/runes")
+    } else {
+        PathBuf::new()
+    };
+    dir.join("puzzle_progress.txt")
+}
+
+/// Plays through the puzzles of a file, answering for the defender.
+pub struct Trainer {
+    pub puzzles: Vec<Puzzle>,
+    pub index: usize,
+    pub game: Game,
+    /// The moves of both sides since the puzzle's start.
+    played: Vec<Move>,
+    /// What became of the current attempt, `None` while it's going on.
+    pub outcome: Option<Outcome>,
+    /// Tells the player how the last move went.
+    pub message: String,
+    pub progress: Progress,
+}
+
+impl Trainer {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Can't read {path}: {e}"))?;
+        let puzzles = parse_puzzles(&text)?;
+        if puzzles.is_empty() {
+            return Err(format!("No puzzles in {path}"));
+        }
+        let mut trainer = Trainer {
+            game: puzzles[0].start.clone(),
+            puzzles,
+            index: 0,
+            played: Vec::new(),
+            outcome: None,
+            message: String::new(),
+            progress: Progress::load(),
+        };
+        trainer.select(0);
+        Ok(trainer)
+    }
+
+    pub fn puzzle(&self) -> &Puzzle {
+        &self.puzzles[self.index]
+    }
+
+    /// Starts the puzzle at `index`, again if it is the current one.
+    pub fn select(&mut self, index: usize) {
+        self.index = index.min(self.puzzles.len() - 1);
+        self.game = self.puzzle().start.clone();
+        self.played.clear();
+        self.outcome = None;
+        let puzzle = self.puzzle();
+        let side = ["Wealth", "Knowledge"][puzzle.start.next_player as usize];
+        self.message = format!("{side} to play and make Joy in {}", puzzle.joy_in);
+    }
+
+    /// The player's move `m`. A move that loses the forced Joy fails the puzzle and isn't
+    /// played.
+    pub fn play(&mut self, m: Move) {
+        if self.outcome.is_some() || !self.game.valid_symbols_at(&m.position).contains(&m.symbol) {
+            return;
+        }
+        let puzzle = &self.puzzles[self.index];
+        if !puzzle.accepts(&self.played, m) {
+            let solution: Vec<String> = puzzle.solution.iter().map(|m| format!("{m}")).collect();
+            self.message = format!("Wrong. The solution is {}", solution.join(" "));
+            self.finish(Outcome::Failed);
+            return;
+        }
+        self.game.apply_move(m).unwrap();
+        self.played.push(m);
+        if self.game.game_over {
+            self.message = format!("Right, {m} wins!");
+            self.finish(Outcome::Solved);
+            return;
+        }
+        match puzzle.reply(&self.played) {
+            Some(reply) if self.game.apply_move(reply).is_ok() => {
+                self.played.push(reply);
+                self.message = format!("Right! The opponent answers {reply}");
+            }
+            _ => self.message = format!("Right, {m}"),
+        }
+    }
+
+    fn finish(&mut self, outcome: Outcome) {
+        self.outcome = Some(outcome);
+        self.progress.set(&self.puzzles[self.index], outcome);
+    }
+}
@@ -7,6 +7,7 @@ use crate::TextureIds;
 pub struct WireGrid {
     pub textures: TextureIds,
     pub board: runes_core::board::Board,
+    pub hints: Vec<Position>,
 }
 
 impl WireGrid {
@@ -74,6 +75,13 @@ impl Widget for WireGrid {
                     );
                 },
             }
+            if self.hints.contains(&Position(i, j)) {
+                ui.painter().rect_stroke(
+                    Rect::from_two_pos(p1, p2).shrink(1.0),
+                    Rounding::same(2.0),
+                    Stroke::new(3.0, Color32::YELLOW),
+                );
+            }
         });
         response
     }
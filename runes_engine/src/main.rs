@@ -0,0 +1,365 @@
+use std::{
+    io::{self, BufRead, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use runes_core::{
+    ai_player::{AiPlayer, AiPlayerMonte, Level},
+    board::Board,
+    game::{Game, Move, Player},
+    mcts::MctsOptions,
+    perft::divide,
+};
+
+/// Board size until the first `newgame`.
+const DEFAULT_SIZE: usize = 13;
+
+/// Largest board of `newgame`; far more than any game needs, yet quick to set up and search.
+const MAX_SIZE: usize = 99;
+
+/// Time limit of `go infinite`, which in practice ends with `stop`.
+const INFINITE: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Mcts,
+    Negamax,
+}
+
+/// A search running in the background.
+struct Search {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+/// The state between commands, see PROTOCOL.md.
+struct Engine<W: Write + Send + 'static> {
+    out: Arc<Mutex<W>>,
+    size: usize,
+    game: Game,
+    kind: Kind,
+    level: Level,
+    seed: Option<u64>,
+    search: Option<Search>,
+}
+
+impl<W: Write + Send + 'static> Engine<W> {
+    fn new(out: W) -> Self {
+        Engine {
+            out: Arc::new(Mutex::new(out)),
+            size: DEFAULT_SIZE,
+            game: Game::new(DEFAULT_SIZE),
+            kind: Kind::Mcts,
+            level: Level::Medium,
+            seed: None,
+            search: None,
+        }
+    }
+
+    fn send(&self, line: &str) {
+        send(&self.out, line);
+    }
+
+    /// Returns `false` when the engine should exit.
+    fn handle(&mut self, line: &str) -> Result<bool, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => (),
+            ["runes"] => {
+                self.send(&format!(
+                    "id name runes_engine {}",
+                    env!("CARGO_PKG_VERSION")
+                ));
+                self.send("option name Engine type combo default mcts var mcts var negamax");
+                self.send(
+                    "option name Level type combo default medium \
+                     var easy var medium var hard var veryhard",
+                );
+                self.send("option name Seed type string default none");
+                self.send("runesok");
+            }
+            ["isready"] => self.send("readyok"),
+            ["setoption", "name", name, "value", value] => self.set_option(name, value)?,
+            ["newgame", size] => {
+                let size = size
+                    .parse()
+                    .ok()
+                    .filter(|size| (1..=MAX_SIZE).contains(size))
+                    .ok_or(format!("Invalid board size `{size}`"))?;
+                self.stop();
+                self.size = size;
+                self.game = Game::new(size);
+            }
+            ["position", rest @ ..] => {
+                let game = self.parse_position(rest)?;
+                self.stop();
+                self.game = game;
+            }
+            ["go", limit @ ..] => {
+                let movetime = match limit {
+                    [] => None,
+                    ["infinite"] => Some(INFINITE),
+                    ["movetime", ms] => Some(Duration::from_millis(
+                        ms.parse().map_err(|_| format!("Invalid movetime `{ms}`"))?,
+                    )),
+                    _ => return Err(format!("Unknown limit `{}`", limit.join(" "))),
+                };
+                self.stop();
+                self.go(movetime);
+            }
+            ["perft", depth] => {
+                let depth = depth
+                    .parse()
+                    .map_err(|_| format!("Invalid depth `{depth}`"))?;
+                let split = divide(&self.game, depth);
+                for (m, count) in &split {
+                    self.send(&format!("{m} {count}"));
+                }
+                let total: u64 = split.iter().map(|(_, count)| count).sum();
+                self.send(&format!("nodes {}", if depth == 0 { 1 } else { total }));
+            }
+            ["stop"] => self.stop(),
+            ["quit"] => {
+                self.stop();
+                return Ok(false);
+            }
+            _ => return Err(format!("Unknown command `{line}`")),
+        }
+        Ok(true)
+    }
+
+    fn set_option(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name.to_lowercase().as_str() {
+            "engine" => {
+                self.kind = match value.to_lowercase().as_str() {
+                    "mcts" => Kind::Mcts,
+                    "negamax" => Kind::Negamax,
+                    _ => return Err(format!("Unknown engine `{value}`")),
+                }
+            }
+            "level" => self.level = value.parse()?,
+            "seed" => {
+                self.seed = match value {
+                    "none" => None,
+                    _ => Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("Invalid seed `{value}`"))?,
+                    ),
+                }
+            }
+            _ => return Err(format!("Unknown option `{name}`")),
+        }
+        Ok(())
+    }
+
+    fn parse_position(&self, words: &[&str]) -> Result<Game, String> {
+        let (mut game, rest) = match words {
+            ["startpos", rest @ ..] => (Game::new(self.size), rest),
+            [board, side, rest @ ..] => {
+                let next_player = match *side {
+                    "W" | "w" => 0,
+                    "K" | "k" => 1,
+                    _ => return Err(format!("Unknown side to move `{side}`")),
+                };
+                let game = Game::from_board(board.parse::<Board>()?, next_player);
+                game.validate()?;
+                (game, rest)
+            }
+            _ => return Err("Missing position".to_string()),
+        };
+        match rest {
+            [] => (),
+            ["moves", moves @ ..] => {
+                for m in moves {
+                    let parsed: Move = m.parse()?;
+                    if game.game_over {
+                        return Err(format!("Move {m} after the end of the game"));
+                    }
+                    game.apply_move(parsed).map_err(|e| format!("{e} {m}"))?;
+                }
+            }
+            _ => return Err(format!("Expected `moves` instead of `{}`", rest.join(" "))),
+        }
+        game.validate()?;
+        Ok(game)
+    }
+
+    fn go(&mut self, movetime: Option<Duration>) {
+        if self.game.game_over {
+            self.send("bestmove none");
+            return;
+        }
+        let player: Box<dyn Player + Send> = match self.kind {
+            Kind::Mcts => match movetime {
+                Some(timeout) => Box::new(AiPlayerMonte::with_options(
+                    self.level,
+                    MctsOptions {
+                        timeout,
+                        seed: self.seed,
+                        ..MctsOptions::default()
+                    },
+                )),
+                None => Box::new(AiPlayerMonte::with_seed(self.level, self.seed)),
+            },
+            Kind::Negamax => Box::new(AiPlayer::new(self.level)),
+        };
+        let stop = player.stop_flag().unwrap();
+        let (done, finished) = mpsc::channel::<()>();
+        if let (Kind::Negamax, Some(movetime)) = (self.kind, movetime) {
+            let stop = stop.clone();
+            thread::spawn(move || {
+                if let Err(RecvTimeoutError::Timeout) = finished.recv_timeout(movetime) {
+                    stop.store(true, Ordering::Relaxed);
+                }
+            });
+        }
+        let game = self.game.clone();
+        let out = self.out.clone();
+        let handle = thread::spawn(move || {
+            let m = player.make_move(game);
+            drop(done);
+            if let Some(info) = player.search_info() {
+                send(&out, &format!("info {info}"));
+            }
+            send(&out, &format!("bestmove {m}"));
+        });
+        self.search = Some(Search { stop, handle });
+    }
+
+    /// Ends the running search; it answers with its best move before this returns.
+    fn stop(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.stop.store(true, Ordering::Relaxed);
+            search.handle.join().unwrap();
+        }
+    }
+
+    /// Lets the running search finish and returns the output.
+    fn finish(mut self) -> W {
+        if let Some(search) = self.search.take() {
+            search.handle.join().unwrap();
+        }
+        match Arc::try_unwrap(self.out) {
+            Ok(out) => out.into_inner().unwrap(),
+            Err(_) => unreachable!("the search thread has ended"),
+        }
+    }
+}
+
+fn send<W: Write>(out: &Mutex<W>, line: &str) {
+    let mut out = out.lock().unwrap();
+    let _ = writeln!(out, "{line}");
+    let _ = out.flush();
+}
+
+/// Answers the commands in `input` until `quit` or its end.
+fn run<W: Write + Send + 'static>(input: impl BufRead, out: W) -> W {
+    let mut engine = Engine::new(out);
+    for line in input.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        match engine.handle(&line) {
+            Ok(true) => (),
+            Ok(false) => break,
+            Err(e) => engine.send(&format!("error {e}")),
+        }
+    }
+    engine.finish()
+}
+
+fn main() {
+    run(io::stdin().lock(), io::stdout());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answers(script: &str) -> Vec<String> {
+        let out = run(script.as_bytes(), Vec::new());
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn handshake_and_errors() {
+        let lines = answers("runes\nisready\nfly\nnewgame 7\nposition startpos moves a1=J\n");
+        assert!(lines[0].starts_with("id name runes_engine"));
+        assert_eq!(lines[4], "runesok");
+        assert_eq!(lines[5], "readyok");
+        assert_eq!(lines[6], "error Unknown command `fly`");
+        assert_eq!(lines[7], "error Invalid move a1=J");
+        assert_eq!(lines.len(), 8);
+    }
+
+    #[test]
+    fn rejects_illegal_positions_and_sizes() {
+        let lines = answers(
+            "newgame 0
+newgame 100000
+\
+             position ..../.W../..../.... K
+position startpos moves swap
+perft 1
+",
+        );
+        assert_eq!(lines[0], "error Invalid board size `0`");
+        assert_eq!(lines[1], "error Invalid board size `100000`");
+        assert_eq!(lines[2], "error W at b2 can't have been placed");
+        assert!(lines[3].starts_with("error"), "{lines:?}");
+        // The position stays the 13x13 start.
+        assert_eq!(lines.last().unwrap(), "nodes 168");
+    }
+
+    #[test]
+    fn perft_divides_by_first_move() {
+        let lines = answers("newgame 5\nperft 2\n");
+        assert_eq!(lines.len(), 25);
+        assert_eq!(lines[0], "a1=B 23");
+        assert_eq!(lines[24], "nodes 576");
+    }
+
+    #[test]
+    fn plays_forced_joy() {
+        // Wealth to move, with a Joy square at d3.
+        let board = "......./.X...../..W.X../...B.../......./......./.......";
+        for engine in ["mcts", "negamax"] {
+            let lines = answers(&format!(
+                "setoption name Engine value {engine}\n\
+                 position {board} W\n\
+                 go movetime 100\n"
+            ));
+            assert!(lines[0].starts_with("info score"), "{lines:?}");
+            assert_eq!(lines[1], "bestmove d3=J");
+        }
+    }
+
+    #[test]
+    fn stop_ends_infinite_search() {
+        for engine in ["mcts", "negamax"] {
+            let lines = answers(&format!(
+                "setoption name Engine value {engine}\n\
+                 setoption name Level value veryhard\n\
+                 newgame 9\n\
+                 position startpos moves d4=X\n\
+                 go infinite\n\
+                 stop\n\
+                 position startpos moves d4=X f6=X\n\
+                 go movetime 100\n"
+            ));
+            let best: Vec<&String> = lines.iter().filter(|l| l.starts_with("bestmove")).collect();
+            assert_eq!(best.len(), 2, "{lines:?}");
+        }
+    }
+}
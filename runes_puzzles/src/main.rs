@@ -0,0 +1,169 @@
+use std::{collections::HashSet, fs};
+
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use runes_core::{
+    game::Game,
+    player_spec::PlayerSpec,
+    puzzle::{write_puzzles, Puzzle},
+    session::Session,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+struct Options {
+    /// Plays both sides of the self-play games.
+    player: PlayerSpec,
+    /// Stop after this many puzzles.
+    count: usize,
+    /// Stop after this many games, even with fewer puzzles.
+    games: usize,
+    board_size: usize,
+    /// The longest puzzles: Joy in at most this many moves.
+    max_moves: usize,
+    /// Random plies played before the players take over, so the games differ.
+    opening_plies: usize,
+    seed: u64,
+    out: String,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            player: PlayerSpec::Casual(runes_core::ai_player::Level::Medium),
+            count: 20,
+            games: 200,
+            board_size: 9,
+            max_moves: 3,
+            opening_plies: 4,
+            seed: 0,
+            out: "puzzles.txt".to_string(),
+        }
+    }
+}
+
+const USAGE: &str = "Usage: runes_puzzles [--player PLAYER] [--count N] [--games N] [--size N] \
+[--max-moves N] [--opening N] [--seed N] [--out FILE]
+Players: random, negamax[:LEVEL], casual[:LEVEL], mcts[:LEVEL|:TIMEms], engine:PROGRAM[@TIMEms]";
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {flag}"));
+        let number = |v: &String| {
+            v.parse::<u64>()
+                .map_err(|_| format!("Invalid number `{v}`"))
+        };
+        match flag.as_str() {
+            "--player" => options.player = value()?.parse()?,
+            "--count" => options.count = number(value()?)? as usize,
+            "--games" => options.games = number(value()?)? as usize,
+            "--size" => options.board_size = number(value()?)? as usize,
+            "--max-moves" => options.max_moves = number(value()?)?.max(2) as usize,
+            "--opening" => options.opening_plies = number(value()?)? as usize,
+            "--seed" => options.seed = number(value()?)?,
+            "--out" => options.out = value()?.clone(),
+            _ => return Err(format!("Unknown option {flag}\n{USAGE}")),
+        }
+    }
+    Ok(options)
+}
+
+/// A fresh game with `plies` random moves played, not yet over.
+fn random_opening(board_size: usize, plies: usize, rng: &mut ChaCha8Rng) -> Game {
+    loop {
+        let mut game = Game::new(board_size);
+        while game.history().len() < plies && !game.game_over {
+            let Some(&m) = game.generate_moves().choose(rng) else {
+                break;
+            };
+            game.apply_move(m).unwrap();
+        }
+        if !game.game_over {
+            return game;
+        }
+    }
+}
+
+/// Plays self-play games and collects the puzzles met on the way, each position once.
+/// `report` is called with every new puzzle and the number of the game it came from.
+fn generate(
+    options: &Options,
+    mut report: impl FnMut(&Puzzle, usize),
+) -> Result<Vec<Puzzle>, String> {
+    let mut rng = ChaCha8Rng::seed_from_u64(options.seed);
+    let mut seen = HashSet::new();
+    let mut puzzles = Vec::new();
+    for game_index in 0..options.games {
+        if puzzles.len() >= options.count {
+            break;
+        }
+        let seed = options.seed.wrapping_add(game_index as u64);
+        let game = random_opening(options.board_size, options.opening_plies, &mut rng);
+        let mut session = Session::with_game(
+            options.player.create(Some(seed))?,
+            options.player.create(Some(seed.wrapping_add(1)))?,
+            game,
+        );
+        session.start_loop(|s| {
+            if puzzles.len() >= options.count {
+                return;
+            }
+            let Some(puzzle) = Puzzle::find(&s.game, options.max_moves) else {
+                return;
+            };
+            let key = (puzzle.start.board.notation(), puzzle.start.next_player);
+            if seen.insert(key) {
+                report(&puzzle, game_index + 1);
+                puzzles.push(puzzle);
+            }
+        });
+    }
+    Ok(puzzles)
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    };
+    let puzzles = generate(&options, |puzzle, game| {
+        println!("Game {game}: Joy in {}", puzzle.joy_in);
+    });
+    let puzzles = match puzzles {
+        Ok(puzzles) => puzzles,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = fs::write(&options.out, write_puzzles(&puzzles)) {
+        eprintln!("Can't write {}: {e}", options.out);
+        std::process::exit(1);
+    }
+    println!("{} puzzles written to {}", puzzles.len(), options.out);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use runes_core::puzzle::parse_puzzles;
+
+    #[test]
+    fn generated_puzzles_read_back() {
+        let args: Vec<String> = "--player random --count 3 --games 50 --size 7 --max-moves 2"
+            .split(' ')
+            .map(String::from)
+            .collect();
+        let options = parse_args(&args).unwrap();
+        let puzzles = generate(&options, |_, _| {}).unwrap();
+        assert_eq!(puzzles.len(), 3);
+        let parsed = parse_puzzles(&write_puzzles(&puzzles)).unwrap();
+        assert!(parsed == puzzles);
+        assert!(parsed.iter().all(|p| p.joy_in == 2));
+    }
+}
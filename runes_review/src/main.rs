@@ -0,0 +1,99 @@
+use std::{fs, time::Duration};
+
+use runes_core::{
+    analysis::{Engine, Limits},
+    record::GameRecord,
+    review::{annotate, html_report, review, Thresholds},
+};
+
+#[derive(Clone, Debug, PartialEq)]
+struct Options {
+    record: String,
+    engine: Engine,
+    /// Where the annotated record goes, standard output without one.
+    out: Option<String>,
+    html: Option<String>,
+}
+
+const USAGE: &str = "Usage: runes_review [--depth N | --time MS] [--out FILE] [--html FILE] RECORD";
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut record = None;
+    let mut engine = Engine::Negamax { depth: 2 };
+    let mut out = None;
+    let mut html = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {arg}"));
+        let number = |v: &String| {
+            v.parse::<u64>()
+                .map_err(|_| format!("Invalid number `{v}`"))
+        };
+        match arg.as_str() {
+            "--depth" => {
+                engine = Engine::Negamax {
+                    depth: number(value()?)?.clamp(1, 8) as u8,
+                }
+            }
+            "--time" => {
+                engine = Engine::Mcts {
+                    time: Duration::from_millis(number(value()?)?),
+                }
+            }
+            "--out" => out = Some(value()?.clone()),
+            "--html" => html = Some(value()?.clone()),
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {arg}\n{USAGE}")),
+            _ => record = Some(arg.clone()),
+        }
+    }
+    Ok(Options {
+        record: record.ok_or(format!("Missing game record\n{USAGE}"))?,
+        engine,
+        out,
+        html,
+    })
+}
+
+fn describe(engine: &Engine) -> String {
+    match engine {
+        Engine::Negamax { depth } => format!("negamax depth {depth}"),
+        Engine::Mcts { time } => format!("mcts {}ms", time.as_millis()),
+    }
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let text = fs::read_to_string(&options.record)
+        .map_err(|e| format!("Can't read {}: {e}", options.record))?;
+    let record: GameRecord = text.parse()?;
+    let limits = Limits {
+        engine: options.engine,
+        ..Limits::default()
+    };
+    let reviews = review(&record, &limits, &Thresholds::for_engine(&options.engine))?;
+    let annotated = annotate(&record, &reviews, &describe(&options.engine));
+    match &options.out {
+        Some(path) => fs::write(path, annotated.to_string())
+            .map_err(|e| format!("Can't write {path}: {e}"))?,
+        None => print!("{annotated}"),
+    }
+    if let Some(path) = &options.html {
+        fs::write(path, html_report(&annotated, &reviews)?)
+            .map_err(|e| format!("Can't write {path}: {e}"))?;
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = run(&options) {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,348 @@
+use std::fs;
+
+use ratatui::crossterm::event::KeyCode;
+use runes_core::{
+    analysis::{analyze, Limits},
+    board::{Field, Position},
+    game::{Game, Move, Player, PLAYER_SYMBOLS},
+    player_spec::PlayerSpec,
+    record::GameRecord,
+};
+
+/// The state of the full-screen game: the position, who plays each side and the cursor.
+pub struct App {
+    pub game: Game,
+    /// The position the game started from.
+    start: Game,
+    /// What plays each side, `None` for a human at the keyboard.
+    pub seats: [Option<PlayerSpec>; 2],
+    seed: Option<u64>,
+    /// The computer players of `seats`.
+    pub players: [Option<Box<dyn Player>>; 2],
+    pub names: [String; 2],
+    pub cursor: Position,
+    /// Tells the player what happened, e.g. why a move wasn't played.
+    pub message: String,
+    /// The command being typed after `:`, e.g. a move like `g7=X`.
+    pub command: Option<String>,
+    pub quit: bool,
+}
+
+/// Headers of a saved game with the seats, next to the names in `Wealth` and `Knowledge`.
+const SEAT_HEADERS: [&str; 2] = ["WealthPlayer", "KnowledgePlayer"];
+
+impl App {
+    /// A game from `game` on, with the computer players of `seats` created from `seed`.
+    pub fn new(
+        game: Game,
+        seats: [Option<PlayerSpec>; 2],
+        seed: Option<u64>,
+    ) -> Result<Self, String> {
+        let mut players: [Option<Box<dyn Player>>; 2] = [None, None];
+        for (i, seat) in seats.iter().enumerate() {
+            if let Some(spec) = seat {
+                let mut player = spec.create(seed.map(|s| s.wrapping_add(i as u64)))?;
+                player.set_symbol(PLAYER_SYMBOLS[i]);
+                players[i] = Some(player);
+            }
+        }
+        let names = [0, 1].map(|i| match &players[i] {
+            Some(player) => player.name(),
+            None => "You".to_string(),
+        });
+        let center = game.board.size / 2;
+        Ok(App {
+            start: game.clone(),
+            game,
+            seats,
+            seed,
+            players,
+            names,
+            cursor: Position(center, center),
+            message: "Arrows or hjkl move, Enter places the best rune, B X W K J place that \
+                one, : types a move or save/load FILE, ? hints, q quits"
+                .to_string(),
+            command: None,
+            quit: false,
+        })
+    }
+
+    /// The game so far with the names and seats, to be resumed with `from_record`.
+    pub fn record(&self) -> GameRecord {
+        let mut record = GameRecord::new(&self.start, self.game.history().to_vec());
+        for i in 0..2 {
+            let seat = self.seats[i]
+                .as_ref()
+                .map_or("human".to_string(), PlayerSpec::to_string);
+            record.set_header(["Wealth", "Knowledge"][i], &self.names[i]);
+            record.set_header(SEAT_HEADERS[i], &seat);
+        }
+        if let Some(seed) = self.seed {
+            record.set_header("Seed", &seed.to_string());
+        }
+        record
+    }
+
+    /// Resumes a game saved by `record`. Sides without a seat header are human. The headers
+    /// name the seats at the end, so after a swap they start on the other sides.
+    pub fn from_record(record: &GameRecord) -> Result<Self, String> {
+        let mut seats = [None, None];
+        for (seat, key) in seats.iter_mut().zip(SEAT_HEADERS) {
+            *seat = match record.header(key) {
+                None | Some("human") => None,
+                Some(spec) => Some(spec.parse()?),
+            };
+        }
+        let mut names = [0, 1].map(|i| record.header(["Wealth", "Knowledge"][i]));
+        let swapped = record.moves.iter().any(Move::is_swap);
+        if swapped {
+            seats.swap(0, 1);
+            names.swap(0, 1);
+        }
+        let seed = match record.header("Seed") {
+            Some(seed) => Some(seed.parse().map_err(|_| "Invalid Seed header")?),
+            None => None,
+        };
+        let mut app = App::new(record.start()?, seats, seed)?;
+        for (name, saved) in app.names.iter_mut().zip(names) {
+            if let Some(saved) = saved {
+                *name = saved.to_string();
+            }
+        }
+        for m in &record.moves {
+            app.apply(*m)
+                .map_err(|e| format!("{e} {m} (move {})", app.game.history().len() + 1))?;
+        }
+        if let Some(m) = app.game.last_move() {
+            app.cursor = m.position;
+        }
+        Ok(app)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.record().to_string()).map_err(|e| format!("Can't write {path}: {e}"))
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Can't read {path}: {e}"))?;
+        let record: GameRecord = text.parse().map_err(|e| format!("{path}: {e}"))?;
+        App::from_record(&record).map_err(|e| format!("{path}: {e}"))
+    }
+
+    /// Ends the computer players' pondering, before the app is dropped or replaced.
+    pub fn stop(&self) {
+        for player in self.players.iter().flatten() {
+            player.stop_pondering();
+        }
+    }
+
+    /// The computer player whose turn it is, if any.
+    pub fn computer_to_move(&self) -> Option<&dyn Player> {
+        if self.game.game_over {
+            return None;
+        }
+        self.players[self.game.next_player as usize].as_deref()
+    }
+
+    /// Lets the computer player to move make its move, it ponders on while the human thinks.
+    pub fn computer_move(&mut self) {
+        let Some(player) = self.computer_to_move() else {
+            return;
+        };
+        let name = &self.names[self.game.next_player as usize];
+        let m = match player.try_make_move(self.game.clone()) {
+            Ok(m) => m,
+            Err(e) => {
+                self.message = format!("{name} forfeits: {e}");
+                self.quit = true;
+                return;
+            }
+        };
+        if let Err(e) = self.game.clone().apply_move(m) {
+            self.message = format!("{name} forfeits: {e} {m}");
+            self.quit = true;
+            return;
+        }
+        self.message = format!("{name} played {m}");
+        self.apply(m).unwrap();
+        // Who moved is not to move now, also after a swap.
+        let mover = 1 - self.game.next_player as usize;
+        if let (Some(player), false) = (&self.players[mover], self.game.game_over) {
+            player.ponder(self.game.clone());
+        }
+    }
+
+    /// Plays `m`. A swap changes the sides of the seats, as in `Session`.
+    fn apply(&mut self, m: Move) -> Result<(), String> {
+        self.game.apply_move(m)?;
+        if m.is_swap() {
+            self.seats.swap(0, 1);
+            self.players.swap(0, 1);
+            self.names.swap(0, 1);
+            for (player, symbol) in self.players.iter_mut().zip(PLAYER_SYMBOLS) {
+                if let Some(player) = player {
+                    player.set_symbol(symbol);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) {
+        if self.command.is_some() {
+            self.edit_command(key);
+            return;
+        }
+        let last = self.game.board.size - 1;
+        let Position(row, col) = self.cursor;
+        match key {
+            KeyCode::Left | KeyCode::Char('h') => {
+                self.cursor = Position(row, col.saturating_sub(1))
+            }
+            KeyCode::Right | KeyCode::Char('l') => self.cursor = Position(row, (col + 1).min(last)),
+            KeyCode::Up | KeyCode::Char('k') => self.cursor = Position(row.saturating_sub(1), col),
+            KeyCode::Down | KeyCode::Char('j') => self.cursor = Position((row + 1).min(last), col),
+            KeyCode::Enter | KeyCode::Char(' ') => self.play(Move::new(
+                self.cursor,
+                self.game.best_symbol_at(&self.cursor),
+            )),
+            // Capitals pick the rune, the small letters move the cursor.
+            KeyCode::Char(c @ ('B' | 'X' | 'W' | 'K' | 'J')) => {
+                let symbol = Field::from_letter(c).unwrap();
+                self.play(Move::new(self.cursor, symbol));
+            }
+            KeyCode::Char(':') => self.command = Some(String::new()),
+            KeyCode::Char('?') if self.computer_to_move().is_none() && !self.game.game_over => {
+                let hints: Vec<String> = analyze(&self.game, &Limits::default())
+                    .iter()
+                    .map(|c| format!("{} ({:+})", c.mv, c.score))
+                    .collect();
+                self.message = format!("Hints: {}", hints.join(", "));
+            }
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            _ => (),
+        }
+    }
+
+    fn edit_command(&mut self, key: KeyCode) {
+        let Some(command) = self.command.as_mut() else {
+            return;
+        };
+        match key {
+            KeyCode::Char(c) => command.push(c),
+            KeyCode::Backspace => {
+                command.pop();
+            }
+            KeyCode::Esc => self.command = None,
+            KeyCode::Enter => {
+                let command = self.command.take().unwrap();
+                self.run_command(command.trim());
+            }
+            _ => (),
+        }
+    }
+
+    /// Carries out a command typed after `:`. A move is a position with a rune, like `g7=X`,
+    /// or just the position for the best rune there.
+    fn run_command(&mut self, command: &str) {
+        if command.is_empty() {
+            return;
+        }
+        match command.split_once(' ') {
+            Some(("save", path)) => {
+                self.message = match self.save(path.trim()) {
+                    Ok(()) => format!("Saved to {}", path.trim()),
+                    Err(e) => e,
+                };
+                return;
+            }
+            Some(("load", path)) => {
+                match App::load(path.trim()) {
+                    Ok(loaded) => {
+                        self.stop();
+                        *self = loaded;
+                        self.message = format!("Loaded {}", path.trim());
+                    }
+                    Err(e) => self.message = e,
+                }
+                return;
+            }
+            _ => (),
+        }
+        let size = self.game.board.size;
+        let off_board = |p: &Position| p.0 >= size || p.1 >= size;
+        if let Ok(position) = command.parse::<Position>() {
+            if off_board(&position) {
+                self.message = format!("{position} is off the board");
+            } else {
+                self.play(Move::new(position, self.game.best_symbol_at(&position)));
+            }
+        } else if let Ok(m) = command.parse::<Move>() {
+            if off_board(&m.position) {
+                self.message = format!("{} is off the board", m.position);
+            } else {
+                self.play(m);
+            }
+        } else {
+            self.message =
+                format!("Unknown command `{command}`, try a move like g7=X or save FILE");
+        }
+    }
+
+    /// Plays `m` for the human to move and puts the cursor on it.
+    fn play(&mut self, m: Move) {
+        if self.game.game_over || self.computer_to_move().is_some() {
+            return;
+        }
+        if !m.is_swap() {
+            self.cursor = m.position;
+        }
+        let name = self.names[self.game.next_player as usize].clone();
+        if self.apply(m).is_ok() {
+            self.message = format!("{name} played {m}");
+        } else if !self.game.board.is_empty(&m.position) {
+            self.message = format!("{} is taken", m.position);
+        } else {
+            let valid: Vec<&str> = self
+                .game
+                .valid_symbols_at(&m.position)
+                .iter()
+                .map(Field::name)
+                .collect();
+            self.message = format!(
+                "{} can't go on {}, {} can",
+                m.symbol.name(),
+                m.position,
+                valid.join(" or ")
+            );
+        }
+    }
+
+    /// What the status bar says about the game.
+    pub fn status(&self) -> String {
+        let side = |player: u8| ["Wealth", "Knowledge"][player as usize];
+        if self.game.game_over {
+            return match self.game.winner() {
+                Some(w) => format!("Game over, {} ({}) wins", side(w), self.names[w as usize]),
+                None => "Game over, the board is full".to_string(),
+            };
+        }
+        let here: Vec<String> = self
+            .game
+            .valid_symbols_at(&self.cursor)
+            .iter()
+            .map(|field| field.letter().to_string())
+            .collect();
+        let next = self.game.next_player;
+        format!(
+            "{} to move | {}: {}",
+            side(next),
+            self.cursor,
+            if here.is_empty() {
+                "taken".to_string()
+            } else {
+                here.join(" ")
+            }
+        )
+    }
+}
@@ -1,120 +1,518 @@
-use std::io::{stdin, stdout, Write};
+mod app;
+mod replay;
+mod ui;
 
+use std::{
+    fs, io,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    thread,
+    time::Duration,
+};
+
+use app::App;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    DefaultTerminal,
+};
+use replay::Replay;
 use runes_core::{
-    //ai_player::{AiPlayer, AiPlayerMonte, Level},
-    ai_player::{AiPlayerMonte, Level},
-    board::Position,
-    game::{Game, Move, Player, PLAYER_SYMBOLS},
-    human_player::HumanPlayer,
+    ai_player::Level,
+    batch::{csv, simulate, summary, Batch},
+    game::Game,
+    player_spec::PlayerSpec,
+    render::BoardRenderer,
 };
 
+#[derive(Clone, Debug, PartialEq)]
+struct Options {
+    board_size: usize,
+    /// The players of Wealth and Knowledge, `None` for a human.
+    seats: [Option<PlayerSpec>; 2],
+    /// Names that replace the players' own.
+    names: [Option<String>; 2],
+    /// Makes the computer players repeatable.
+    seed: Option<u64>,
+    /// The side that moves first.
+    first: u8,
+    /// A saved game to resume, with its own players.
+    load: Option<String>,
+    /// Plays this many games between the computer seats instead of showing the board.
+    batch: Option<usize>,
+    /// A saved game to step through instead of playing.
+    replay: Option<String>,
+    /// Time per move of the replay's autoplay.
+    speed: Duration,
+    /// Where batch games are written as CSV, standard output if not given.
+    csv: Option<String>,
+    threads: Option<usize>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            board_size: 13,
+            seats: [Some(PlayerSpec::Mcts(Level::Easy)), None],
+            names: [None, None],
+            seed: None,
+            first: 0,
+            load: None,
+            replay: None,
+            speed: Duration::from_secs(1),
+            batch: None,
+            csv: None,
+            threads: None,
+        }
+    }
+}
+
+const USAGE: &str = "Usage: runes_tui [--size N] [--wealth SEAT] [--knowledge SEAT] \
+[--wealth-name NAME] [--knowledge-name NAME] [--seed N] [--first wealth|knowledge] \
+[--load FILE]
+       runes_tui --batch N --wealth SEAT --knowledge SEAT [--size N] [--seed N] \
+[--first wealth|knowledge] [--threads N] [--csv FILE]
+       runes_tui --replay FILE [--speed MS]
+Seats: human, random, negamax[:LEVEL], casual[:LEVEL], mcts[:LEVEL|:TIMEms], engine:PROGRAM[@TIMEms]
+Levels: easy, medium, hard, veryhard. Default: --wealth mcts:easy --knowledge human";
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {arg}"));
+        let seat = |v: &String| match v.as_str() {
+            "human" => Ok(None),
+            _ => v.parse().map(Some),
+        };
+        let number = |v: &String| {
+            v.parse::<u64>()
+                .map_err(|_| format!("Invalid number `{v}`"))
+        };
+        match arg.as_str() {
+            "--size" => options.board_size = number(value()?)? as usize,
+            "--wealth" => options.seats[0] = seat(value()?)?,
+            "--knowledge" => options.seats[1] = seat(value()?)?,
+            "--wealth-name" => options.names[0] = Some(value()?.clone()),
+            "--knowledge-name" => options.names[1] = Some(value()?.clone()),
+            "--seed" => options.seed = Some(number(value()?)?),
+            "--first" => {
+                options.first = match value()?.as_str() {
+                    "wealth" => 0,
+                    "knowledge" => 1,
+                    v => return Err(format!("Unknown side `{v}`\n{USAGE}")),
+                }
+            }
+            "--load" => options.load = Some(value()?.clone()),
+            "--replay" => options.replay = Some(value()?.clone()),
+            "--speed" => options.speed = Duration::from_millis(number(value()?)?),
+            "--batch" => options.batch = Some(number(value()?)?.max(1) as usize),
+            "--csv" => options.csv = Some(value()?.clone()),
+            "--threads" => options.threads = Some(number(value()?)?.max(1) as usize),
+            "--help" | "-h" => return Err(USAGE.to_string()),
+            _ => return Err(format!("Unknown option {arg}\n{USAGE}")),
+        }
+    }
+    if options.board_size < 3 {
+        return Err("The board needs a size of at least 3".to_string());
+    }
+    if options.batch.is_some() && options.seats.iter().any(Option::is_none) {
+        return Err("Batch games need two computer seats, e.g. --knowledge random".to_string());
+    }
+    Ok(options)
+}
+
+/// Plays the batch of `options`, writes the CSV and prints the summary.
+fn run_batch(options: &Options, games: usize) -> Result<(), String> {
+    let [Some(wealth), Some(knowledge)] = options.seats.clone() else {
+        return Err("Batch games need two computer seats".to_string());
+    };
+    let mut batch = Batch::new([wealth, knowledge], options.board_size, games);
+    batch.first = options.first;
+    batch.seed = options.seed.unwrap_or(0);
+    if let Some(threads) = options.threads {
+        batch.threads = threads;
+    }
+    let done = AtomicUsize::new(0);
+    let stats = simulate(&batch, |_| {
+        let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+        eprint!("\r{done}/{games} games");
+    })?;
+    eprintln!();
+    match &options.csv {
+        Some(path) => {
+            fs::write(path, csv(&stats)).map_err(|e| format!("Can't write {path}: {e}"))?
+        }
+        None => print!("{}", csv(&stats)),
+    }
+    eprint!("{}", summary(&batch, &stats));
+    Ok(())
+}
+
+/// The game and players `options` ask for.
+fn create_app(options: &Options) -> Result<App, String> {
+    if let Some(path) = &options.load {
+        return App::load(path);
+    }
+    let board = Game::new(options.board_size).board;
+    let game = Game::from_board(board, options.first);
+    let mut app = App::new(game, options.seats.clone(), options.seed)?;
+    for (name, given) in app.names.iter_mut().zip(&options.names) {
+        if let Some(given) = given {
+            name.clone_from(given);
+        }
+    }
+    Ok(app)
+}
+
 fn main() {
-    let player1 = Box::new(AiPlayerMonte::new(Level::Easy));
-    //let player1 = Box::new(AiPlayer::new(Level::Medium));
-    //let player2 = Box::new(AiPlayerMonte::new(Level::Easy));
-    let player2 = Box::new(HumanPlayer::new("Apa".to_string(), make_move));
-    let mut my_session: Session = Session::new(player1, player2, 13);
-    let printout = |s: &Session| {
-        println!("{}", s.game.board);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    };
+    if let Some(path) = &options.replay {
+        let mut replay = match Replay::load(path, options.speed) {
+            Ok(replay) => replay,
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        };
+        let mut terminal = ratatui::init();
+        let result = run_replay(&mut terminal, &mut replay);
+        ratatui::restore();
+        if let Err(e) = result {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(games) = options.batch {
+        if let Err(e) = run_batch(&options, games) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    let mut app = match create_app(&options) {
+        Ok(app) => app,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
     };
-    my_session.start_loop(printout);
-    if let Some(s) = my_session.winner() {
-        println!("Game over. Winner is {s}");
-    } else {
-        println!("Game over. Board is full.");
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut app);
+    ratatui::restore();
+    app.stop();
+    if let Err(e) = result {
+        eprintln!("{e}");
+        std::process::exit(1);
     }
-    printout(&my_session);
+    print!("{}", BoardRenderer::for_terminal().render(&app.game.board));
+    println!("{}", app.status());
 }
 
-// fn try_out() {
-//     let mut player1 = HumanPlayer::new("Player1".to_string(), make_move);
-//     let mut player2 = HumanPlayer::new("Player2".to_string(), make_move);
-
-//     let mut my_game: Game = Game::new(&mut player1, &mut player2, 13);
-
-//     my_game
-//         .apply_move(Move::new(Position(7, 6), Field::Gift))
-//         .unwrap();
-//     my_game
-//         .apply_move(Move::new(Position(6, 5), Field::Knowledge))
-//         .unwrap();
-//     println!("{:?}", my_game.board);
-
-//     let best = my_game.best_symbol_at(&Position(2, 3));
-//     println!("Best1 (2,3): {:?}", best);
-
-//     my_game.next_player = my_game.player2;
-//     let best = my_game.best_symbol_at(&Position(7, 6));
-//     println!("Best2 (7,6): {:?}", best);
-
-//     my_game.next_player = my_game.player1;
-//     let best = my_game.best_symbol_at(&Position(7, 5));
-//     println!("Best1 (7,5): {:?}", best);
-
-//     my_game.next_player = my_game.player2;
-//     let best = my_game.best_symbol_at(&Position(7, 5));
-//     println!("Best2 (7,5): {:?}", best);
-// }
-
-fn make_move(player: &HumanPlayer, game: Game) -> Move {
-    println!("Your Turn: {} ({})", player.name, player.symbol);
-    loop {
-        print!("Move (row, col): ");
-        stdout().flush().unwrap();
-        let mut input = String::new();
-        stdin().read_line(&mut input).unwrap();
-        if let Some(p) = input
-            .split_once(',')
-            .and_then(|s| Some(Position(s.0.trim().parse().ok()?, s.1.trim().parse().ok()?)))
-        {
-            let p = Position(p.0 - 1, p.1 - 1);
-            let m = Move::new(p, game.best_symbol_at(&p));
-            if game.is_valid_move(&m) {
-                return m;
-            } else {
-                println!("Invalid move. Try again!");
+/// Redraws after every key and computer move until the player quits, also while the computer
+/// plays both sides.
+fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
+    while !app.quit {
+        if app.computer_to_move().is_some() {
+            app.message = format!(
+                "{} is thinking...",
+                app.names[app.game.next_player as usize]
+            );
+            terminal.draw(|frame| ui::draw(frame, app))?;
+            computer_move(app)?;
+            continue;
+        }
+        terminal.draw(|frame| ui::draw(frame, app))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press {
+                app.handle_key(key.code);
             }
-        } else {
-            println!("Wrong input. Write row number and column number separated by a comma.");
         }
     }
+    Ok(())
 }
 
-pub struct Session {
-    pub players: [Box<dyn Player>; 2],
-    pub game: Game,
+/// Lets the computer move while the keys are still read: `q` or Esc cuts its search short,
+/// and all keys are handled once the move is made.
+fn computer_move(app: &mut App) -> io::Result<()> {
+    let stop = app.computer_to_move().and_then(|player| player.stop_flag());
+    let done = AtomicBool::new(false);
+    let keys = thread::scope(|scope| {
+        let reader = scope.spawn(|| -> io::Result<Vec<KeyCode>> {
+            let mut keys = Vec::new();
+            while !done.load(Ordering::Relaxed) {
+                if !event::poll(Duration::from_millis(50))? {
+                    continue;
+                }
+                if let Event::Key(key) = event::read()? {
+                    if key.kind != KeyEventKind::Press {
+                        continue;
+                    }
+                    if matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                        if let Some(stop) = &stop {
+                            stop.store(true, Ordering::Relaxed);
+                        }
+                    }
+                    keys.push(key.code);
+                }
+            }
+            Ok(keys)
+        });
+        app.computer_move();
+        done.store(true, Ordering::Relaxed);
+        reader.join().unwrap()
+    })?;
+    for key in keys {
+        app.handle_key(key);
+    }
+    Ok(())
 }
 
-impl Session {
-    pub fn new(player1: Box<dyn Player>, player2: Box<dyn Player>, board_size: usize) -> Self {
-        let mut players = [player1, player2];
-        players[0].set_symbol(PLAYER_SYMBOLS[0]);
-        players[1].set_symbol(PLAYER_SYMBOLS[1]);
-        Self {
-            players,
-            game: Game::new(board_size),
-        }
-    }
-
-    pub fn start_loop(&mut self, callback: fn(&Self)) {
-        while !self.game.game_over {
-            callback(self);
-            let next_player = self.game.next_player;
-            let player_move = self.players[next_player as usize].make_move(self.game.clone());
-            match self.game.apply_move(player_move) {
-                Ok(_) => (),
-                Err(s) => println!("{s}"),
+/// Redraws after every key and autoplay move until the viewer quits.
+fn run_replay(terminal: &mut DefaultTerminal, replay: &mut Replay) -> io::Result<()> {
+    while !replay.quit {
+        terminal.draw(|frame| ui::draw_replay(frame, replay))?;
+        // While playing, the next move is due when no key comes in time.
+        if replay.playing && !event::poll(replay.speed)? {
+            replay.tick();
+            continue;
+        }
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press {
+                replay.handle_key(key.code);
             }
         }
     }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{backend::TestBackend, Terminal};
+    use runes_core::{
+        board::{Field, Position},
+        game::Move,
+        record::GameRecord,
+        session::Session,
+    };
+
+    use super::*;
+
+    fn screen(app: &App) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(40, 13)).unwrap();
+        terminal.draw(|frame| ui::draw(frame, app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+                    + "\n"
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cursor_moves_and_places() {
+        let seats = [None, Some(PlayerSpec::Random)];
+        let mut app = App::new(Game::new(7), seats, Some(1)).unwrap();
+        assert_eq!(app.cursor, Position(3, 3));
+        for key in [
+            KeyCode::Char('k'),
+            KeyCode::Up,
+            KeyCode::Left,
+            KeyCode::Char('h'),
+        ] {
+            app.handle_key(key);
+        }
+        assert_eq!(app.cursor, Position(1, 1));
+        for _ in 0..3 {
+            app.handle_key(KeyCode::Char('k'));
+        }
+        assert_eq!(app.cursor, Position(0, 1));
+        assert!(screen(&app).contains("Wealth to move | b1: B"));
+
+        app.handle_key(KeyCode::Enter);
+        assert_eq!(app.game.board.field_at(&Position(0, 1)), Field::Birth);
+        assert_eq!(app.game.last_move().unwrap().position, Position(0, 1));
+        assert!(app.computer_to_move().is_some());
+        // It's not the human's turn.
+        app.handle_key(KeyCode::Char('j'));
+        app.handle_key(KeyCode::Enter);
+        assert_eq!(app.game.history().len(), 1);
 
-    pub fn winner(&self) -> Option<String> {
-        self.game
-            .winner()
-            .map(|idx| self.players[idx as usize].name())
+        app.computer_move();
+        assert_eq!(app.game.history().len(), 2);
+        let screen = screen(&app);
+        assert!(screen.contains("Wealth: You  Knowledge: AI Dumb"));
+        assert!(screen.contains("│1  .  B  .  .  .  .  ."));
+        assert!(screen.contains("│7  "));
+        assert!(screen.contains("│   a  b  c  d  e  f  g"));
+        app.handle_key(KeyCode::Char('q'));
+        assert!(app.quit);
     }
 
-    pub fn reset(&mut self) {
-        self.game.reset();
+    #[test]
+    fn humans_choose_the_rune() {
+        let mut app = App::new(Game::new(7), [None, None], None).unwrap();
+        let command = |app: &mut App, text: &str| {
+            app.handle_key(KeyCode::Char(':'));
+            for c in text.chars() {
+                app.handle_key(KeyCode::Char(c));
+            }
+            app.handle_key(KeyCode::Enter);
+        };
+        command(&mut app, "e4=X");
+        assert_eq!(app.game.board.field_at(&Position(3, 4)), Field::Gift);
+        // A Gift where Knowledge could go too.
+        app.cursor = Position(2, 3);
+        assert_eq!(app.game.valid_symbols_at(&app.cursor).len(), 2);
+        app.handle_key(KeyCode::Char('X'));
+        assert_eq!(app.game.board.field_at(&Position(2, 3)), Field::Gift);
+        command(&mut app, "e5");
+        assert_eq!(app.game.board.field_at(&Position(4, 4)), Field::Wealth);
+        assert_eq!(app.cursor, Position(4, 4));
+
+        command(&mut app, "A1 K");
+        assert_eq!(app.message, "Knowledge can't go on a1, Birth can");
+        command(&mut app, "i9x");
+        assert_eq!(app.message, "i9 is off the board");
+        command(&mut app, "d4");
+        assert_eq!(app.message, "d4 is taken");
+        assert_eq!(app.game.history().len(), 3);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn save_and_load_resume_the_game() {
+        let args: Vec<String> = "--size 7 --wealth random --wealth-name Bot --seed 5 \
+            --first knowledge"
+            .split_whitespace()
+            .map(String::from)
+            .collect();
+        let mut app = create_app(&parse_args(&args).unwrap()).unwrap();
+        app.handle_key(KeyCode::Enter);
+        app.computer_move();
+        app.handle_key(KeyCode::Char('l'));
+        app.handle_key(KeyCode::Char('X'));
+        let path = std::env::temp_dir().join(format!("runes_tui_{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        for c in format!(":save {path}").chars() {
+            app.handle_key(KeyCode::Char(c));
+        }
+        app.handle_key(KeyCode::Enter);
+        assert_eq!(app.message, format!("Saved to {path}"));
+
+        let args = vec!["--load".to_string(), path.to_string()];
+        let loaded = create_app(&parse_args(&args).unwrap()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert!(loaded.game == app.game);
+        assert_eq!(loaded.game.next_player, 0);
+        assert_eq!(loaded.seats, [Some(PlayerSpec::Random), None]);
+        assert_eq!(loaded.names, ["Bot", "You"]);
+        assert!(loaded.computer_to_move().is_some());
+        assert_eq!(loaded.record(), app.record());
+    }
+
+    #[test]
+    fn swaps_change_the_seats() {
+        let mut game = Game::new(7);
+        game.pie_rule = true;
+        let mut app = App::new(game, [Some(PlayerSpec::Random), None], Some(2)).unwrap();
+        app.computer_move();
+        for c in ":swap".chars() {
+            app.handle_key(KeyCode::Char(c));
+        }
+        app.handle_key(KeyCode::Enter);
+        assert_eq!(app.game.history()[1], Move::SWAP);
+        // The human took over Wealth, Knowledge is still to move and now the computer.
+        assert_eq!(app.seats, [None, Some(PlayerSpec::Random)]);
+        assert_eq!(app.names[0], "You");
+        assert!(app.computer_to_move().is_some());
+
+        let loaded = App::from_record(&app.record()).unwrap();
+        assert!(loaded.game == app.game);
+        assert_eq!(loaded.seats, app.seats);
+        assert_eq!(loaded.names, app.names);
+        assert_eq!(loaded.record(), app.record());
+    }
+
+    #[test]
+    fn replay_steps_through_a_record() {
+        let players = [PlayerSpec::Random, PlayerSpec::Random].map(|p| p.create(Some(4)).unwrap());
+        let [wealth, knowledge] = players;
+        let mut session = Session::new(wealth, knowledge, 7);
+        session.start_loop(|_| {});
+        let record = GameRecord::from_session(&session);
+        let plies = record.moves.len();
+        let mut replay = Replay::new(record, Duration::from_millis(300)).unwrap();
+        assert_eq!(replay.game().history().len(), 0);
+        replay.handle_key(KeyCode::Right);
+        replay.handle_key(KeyCode::Char('l'));
+        replay.handle_key(KeyCode::Left);
+        assert_eq!(replay.ply, 1);
+        for key in [':', '3', 'x', 'q'].map(KeyCode::Char) {
+            replay.handle_key(key);
+        }
+        replay.handle_key(KeyCode::Enter);
+        assert_eq!(replay.ply, 3);
+        assert!(!replay.quit);
+
+        let mut terminal = Terminal::new(TestBackend::new(60, 20)).unwrap();
+        terminal
+            .draw(|frame| ui::draw_replay(frame, &replay))
+            .unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains(&format!("Ply 3/{plies}, paused")));
+        assert!(screen.contains(&format!("  3. W {}", session.moves[2])));
+
+        replay.handle_key(KeyCode::Char(' '));
+        while replay.playing {
+            replay.tick();
+        }
+        assert_eq!(replay.ply, plies);
+        assert!(replay.game().game_over);
+        assert!(replay.ending().ends_with("(joy)") || replay.ending().ends_with("(full board)"));
+    }
+
+    #[test]
+    fn options_set_up_the_match() {
+        let args: Vec<String> = "--size 9 --wealth human --knowledge negamax:hard \
+            --wealth-name Ann --seed 3 --first knowledge"
+            .split_whitespace()
+            .map(String::from)
+            .collect();
+        let options = parse_args(&args).unwrap();
+        assert_eq!(
+            options.seats,
+            [None, Some(PlayerSpec::Negamax(Level::Hard))]
+        );
+        let app = create_app(&options).unwrap();
+        assert_eq!(app.game.board.size, 9);
+        assert_eq!(app.game.next_player, 1);
+        assert_eq!(app.names[0], "Ann");
+        assert!(app.computer_to_move().is_some());
+
+        for bad in [
+            "--wealth chess",
+            "--first both",
+            "--size 2",
+            "--seed",
+            "--batch 3",
+        ] {
+            let args: Vec<String> = bad.split(' ').map(String::from).collect();
+            assert!(parse_args(&args).is_err(), "{bad}");
+        }
+    }
+}
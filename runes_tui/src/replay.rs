@@ -0,0 +1,131 @@
+use std::{fs, time::Duration};
+
+use ratatui::crossterm::event::KeyCode;
+use runes_core::{game::Game, record::GameRecord};
+
+/// Autoplay can't go faster or slower than this per move.
+const SPEEDS: (Duration, Duration) = (Duration::from_millis(100), Duration::from_secs(10));
+
+/// Steps through a saved game.
+pub struct Replay {
+    pub record: GameRecord,
+    /// The positions before the first and after each move.
+    positions: Vec<Game>,
+    /// The number of moves played on the board shown.
+    pub ply: usize,
+    /// Plays a move every `speed` while set.
+    pub playing: bool,
+    pub speed: Duration,
+    /// The ply being typed after `:`.
+    pub jump: Option<String>,
+    pub quit: bool,
+}
+
+impl Replay {
+    pub fn new(record: GameRecord, speed: Duration) -> Result<Self, String> {
+        let mut game = record.start()?;
+        let mut positions = vec![game.clone()];
+        for (i, m) in record.moves.iter().enumerate() {
+            game.apply_move(*m)
+                .map_err(|e| format!("{e} {m} (move {})", i + 1))?;
+            positions.push(game.clone());
+        }
+        Ok(Replay {
+            record,
+            positions,
+            ply: 0,
+            playing: false,
+            speed: speed.clamp(SPEEDS.0, SPEEDS.1),
+            jump: None,
+            quit: false,
+        })
+    }
+
+    pub fn load(path: &str, speed: Duration) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Can't read {path}: {e}"))?;
+        let record: GameRecord = text.parse().map_err(|e| format!("{path}: {e}"))?;
+        Replay::new(record, speed).map_err(|e| format!("{path}: {e}"))
+    }
+
+    /// The position after `ply` moves.
+    pub fn game(&self) -> &Game {
+        &self.positions[self.ply]
+    }
+
+    /// The side that made each move, 0 for Wealth.
+    pub fn movers(&self) -> impl Iterator<Item = u8> + '_ {
+        self.positions[..self.record.moves.len()]
+            .iter()
+            .map(|game| game.next_player)
+    }
+
+    pub fn last_ply(&self) -> usize {
+        self.record.moves.len()
+    }
+
+    /// How the game ended, or that it didn't.
+    pub fn ending(&self) -> String {
+        let result = self.record.header("Result").unwrap_or("*");
+        let last = &self.positions[self.last_ply()];
+        let termination = match self.record.header("Termination") {
+            Some(termination) => termination.to_string(),
+            None if last.winner().is_some() => "joy".to_string(),
+            None if last.game_over => "full board".to_string(),
+            None => "unfinished".to_string(),
+        };
+        format!("{result} ({termination})")
+    }
+
+    /// Moves on by one ply while playing, and stops at the end.
+    pub fn tick(&mut self) {
+        if self.playing {
+            self.go_to(self.ply + 1);
+            self.playing = self.ply < self.last_ply();
+        }
+    }
+
+    fn go_to(&mut self, ply: usize) {
+        self.ply = ply.min(self.last_ply());
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) {
+        if let Some(jump) = self.jump.as_mut() {
+            match key {
+                KeyCode::Char(c) if c.is_ascii_digit() => jump.push(c),
+                KeyCode::Backspace => {
+                    jump.pop();
+                }
+                KeyCode::Enter => {
+                    if let Ok(ply) = self.jump.take().unwrap().parse() {
+                        self.go_to(ply);
+                    }
+                }
+                KeyCode::Esc => self.jump = None,
+                _ => (),
+            }
+            return;
+        }
+        match key {
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Char('n') => self.go_to(self.ply + 1),
+            KeyCode::Left | KeyCode::Char('h') | KeyCode::Char('p') => {
+                self.go_to(self.ply.saturating_sub(1))
+            }
+            KeyCode::Home | KeyCode::Char('g') => self.go_to(0),
+            KeyCode::End | KeyCode::Char('G') => self.go_to(self.last_ply()),
+            KeyCode::Char(' ') => {
+                if self.ply == self.last_ply() {
+                    self.go_to(0);
+                }
+                self.playing = !self.playing;
+            }
+            KeyCode::Char('+') => self.speed = (self.speed / 2).max(SPEEDS.0),
+            KeyCode::Char('-') => self.speed = (self.speed * 2).min(SPEEDS.1),
+            KeyCode::Char(':') => self.jump = Some(String::new()),
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            _ => (),
+        }
+        if self.ply == self.last_ply() {
+            self.playing = false;
+        }
+    }
+}
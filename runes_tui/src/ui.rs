@@ -0,0 +1,146 @@
+use ratatui::{
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, List, ListState, Paragraph},
+    Frame,
+};
+use runes_core::{
+    board::{column_name, Field, Position},
+    game::Game,
+};
+
+use crate::{app::App, replay::Replay};
+
+/// Each field takes this many columns.
+const FIELD_WIDTH: usize = 3;
+
+/// Draws the players, the board and the status bar.
+pub fn draw(frame: &mut Frame, app: &App) {
+    let [players, board, status, message] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(0),
+        Constraint::Length(1),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    frame.render_widget(
+        Paragraph::new(format!(
+            "Wealth: {}  Knowledge: {}",
+            app.names[0], app.names[1]
+        )),
+        players,
+    );
+    frame.render_widget(
+        Paragraph::new(board_lines(&app.game, Some(app.cursor)))
+            .block(Block::bordered().title(" Runes ")),
+        board,
+    );
+    frame.render_widget(
+        Paragraph::new(app.status()).style(Style::new().add_modifier(Modifier::REVERSED)),
+        status,
+    );
+    match &app.command {
+        Some(command) => frame.render_widget(Paragraph::new(format!(":{command}")), message),
+        None => frame.render_widget(Paragraph::new(app.message.as_str()), message),
+    }
+}
+
+/// Draws the board of a replay with the move list and how the game ended next to it.
+pub fn draw_replay(frame: &mut Frame, replay: &Replay) {
+    let [main, status] =
+        Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+    let board_width = (replay.game().board.size * FIELD_WIDTH + 5) as u16;
+    let [board, side] =
+        Layout::horizontal([Constraint::Length(board_width), Constraint::Min(20)]).areas(main);
+    let [info, moves] = Layout::vertical([Constraint::Length(6), Constraint::Min(0)]).areas(side);
+
+    frame.render_widget(
+        Paragraph::new(board_lines(replay.game(), None)).block(Block::bordered().title(" Replay ")),
+        board,
+    );
+    let record = &replay.record;
+    let name = |key| record.header(key).unwrap_or("?");
+    let autoplay = if replay.playing {
+        format!("playing, {}ms a move", replay.speed.as_millis())
+    } else {
+        "paused".to_string()
+    };
+    let info_lines = vec![
+        Line::from(format!("Wealth: {}", name("Wealth"))),
+        Line::from(format!("Knowledge: {}", name("Knowledge"))),
+        Line::from(format!(
+            "Ply {}/{}, {autoplay}",
+            replay.ply,
+            replay.last_ply()
+        )),
+        Line::from(format!("Result: {}", replay.ending())),
+    ];
+    frame.render_widget(Paragraph::new(info_lines).block(Block::bordered()), info);
+
+    let items: Vec<String> = record
+        .moves
+        .iter()
+        .zip(replay.movers())
+        .enumerate()
+        .map(|(i, (m, mover))| format!("{:>3}. {} {m}", i + 1, ["W", "K"][mover as usize]))
+        .collect();
+    let mut state = ListState::default().with_selected(replay.ply.checked_sub(1));
+    frame.render_stateful_widget(
+        List::new(items)
+            .block(Block::bordered().title(" Moves "))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+        moves,
+        &mut state,
+    );
+
+    let help = match &replay.jump {
+        Some(jump) => format!("Go to ply: {jump}"),
+        None => "←/→ step, Home/End, Space autoplay, +/- speed, : go to ply, q quits".to_string(),
+    };
+    frame.render_widget(Paragraph::new(help), status);
+}
+
+/// The board with column letters and row numbers, the cursor and the last move highlighted.
+fn board_lines(game: &Game, cursor: Option<Position>) -> Vec<Line<'static>> {
+    let size = game.board.size;
+    let last = game.last_move().map(|m| m.position);
+    let label = size.to_string().len();
+    let mut header = " ".repeat(label + 1);
+    for col in 0..size {
+        header.push_str(&format!("{:^FIELD_WIDTH$}", column_name(col)));
+    }
+    let mut lines = vec![Line::from(header)];
+    for row in 0..size {
+        let mut spans = vec![Span::raw(format!("{:>label$} ", row + 1))];
+        for col in 0..size {
+            let pos = Position(row, col);
+            let field = game.board.field_at(&pos);
+            let mut style = field_style(field);
+            if last == Some(pos) {
+                style = style.bg(Color::DarkGray).add_modifier(Modifier::BOLD);
+            }
+            if cursor == Some(pos) {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            spans.push(Span::styled(
+                format!("{:^FIELD_WIDTH$}", field.letter()),
+                style,
+            ));
+        }
+        lines.push(Line::from(spans));
+    }
+    lines
+}
+
+fn field_style(field: Field) -> Style {
+    let color = match field {
+        Field::Empty => Color::DarkGray,
+        Field::Birth => Color::Blue,
+        Field::Gift => Color::Green,
+        Field::Wealth => Color::Yellow,
+        Field::Knowledge => Color::Magenta,
+        Field::Joy => Color::Red,
+    };
+    Style::new().fg(color)
+}
@@ -0,0 +1,204 @@
+use std::{fs, thread};
+
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use runes_core::{
+    ai_player::{AiPlayer, Level, Weights},
+    batch::run_jobs,
+    game::Game,
+    session::Session,
+};
+
+/// Random plies played before the engines take over, so the games of an iteration differ.
+const OPENING_PLIES: usize = 4;
+
+#[derive(Clone, Debug, PartialEq)]
+struct Options {
+    iterations: usize,
+    games: usize,
+    board_size: usize,
+    level: Level,
+    seed: u64,
+    threads: usize,
+    start: Weights,
+    out: String,
+    /// Log the search of every move.
+    verbose: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            iterations: 50,
+            games: 16,
+            board_size: 9,
+            level: Level::Easy,
+            seed: 0,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            start: Weights::default(),
+            out: "weights.txt".to_string(),
+            verbose: false,
+        }
+    }
+}
+
+const USAGE: &str = "Usage: runes_tune [--iterations N] [--games N] [--size N] [--level LEVEL] \
+[--seed N] [--threads N] [--start FILE] [--out FILE] [--verbose]";
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {flag}"));
+        let number = |v: &String| {
+            v.parse::<u64>()
+                .map_err(|_| format!("Invalid number `{v}`"))
+        };
+        match flag.as_str() {
+            "--iterations" => options.iterations = number(value()?)? as usize,
+            "--games" => options.games = (number(value()?)?.max(2) / 2 * 2) as usize,
+            "--size" => options.board_size = number(value()?)? as usize,
+            "--level" => options.level = value()?.parse()?,
+            "--seed" => options.seed = number(value()?)?,
+            "--threads" => options.threads = number(value()?)?.max(1) as usize,
+            "--start" => {
+                let path = value()?;
+                options.start = fs::read_to_string(path)
+                    .map_err(|e| format!("Can't read {path}: {e}"))?
+                    .parse()?;
+            }
+            "--out" => options.out = value()?.clone(),
+            "--verbose" => options.verbose = true,
+            _ => return Err(format!("Unknown option {flag}\n{USAGE}")),
+        }
+    }
+    Ok(options)
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    };
+    let best = tune(&options, |iteration, score, weights| {
+        println!("Iteration {iteration}: score {score:+.3}, weights {weights:?}")
+    });
+    match fs::write(&options.out, best.to_string()) {
+        Ok(_) => println!("Best weights written to {}", options.out),
+        Err(e) => eprintln!("Can't write {}: {e}", options.out),
+    }
+}
+
+/// SPSA: each iteration plays `theta + c * delta` against `theta - c * delta` for a random
+/// sign vector `delta`, and moves `theta` in the direction of the winner.
+fn tune(options: &Options, report: impl Fn(usize, f64, &Weights)) -> Weights {
+    const A: f64 = 10.0;
+    const C: f64 = 2.0;
+    let stability = options.iterations as f64 / 10.0;
+    let mut rng = ChaCha8Rng::seed_from_u64(options.seed);
+    let mut theta: Vec<f64> = options.start.to_vec().iter().map(|&w| w as f64).collect();
+
+    for k in 0..options.iterations {
+        let a_k = A / (k as f64 + 1.0 + stability).powf(0.602);
+        let c_k = C / (k as f64 + 1.0).powf(0.101);
+        let delta: Vec<f64> = theta
+            .iter()
+            .map(|_| if rng.gen() { 1.0 } else { -1.0 })
+            .collect();
+        let plus = to_weights(theta.iter().zip(&delta).map(|(t, d)| t + c_k * d));
+        let minus = to_weights(theta.iter().zip(&delta).map(|(t, d)| t - c_k * d));
+        let score = play_match(options, plus, minus, rng.gen());
+        for (t, d) in theta.iter_mut().zip(&delta) {
+            *t += a_k * score * d / (2.0 * c_k);
+        }
+        report(k + 1, score, &to_weights(theta.iter().copied()));
+    }
+    to_weights(theta.into_iter())
+}
+
+fn to_weights(values: impl Iterator<Item = f64>) -> Weights {
+    let rounded: Vec<i16> = values.map(|v| v.round() as i16).collect();
+    Weights::from_slice(&rounded)
+}
+
+/// Plays `options.games` games, in pairs from the same opening with colors swapped,
+/// spread over `options.threads` threads. Returns the score of `first` in [-1, 1].
+fn play_match(options: &Options, first: Weights, second: Weights, seed: u64) -> f64 {
+    let pairs = options.games / 2;
+    // The games can't fail.
+    let results = run_jobs(
+        pairs,
+        options.threads,
+        |pair| {
+            let opening = random_opening(options.board_size, seed, pair as u64);
+            Ok(play_game(options, first, second, opening.clone(), pair)
+                - play_game(options, second, first, opening, pair))
+        },
+        |_| true,
+    );
+    let total: i32 = results.unwrap().into_iter().sum();
+    total as f64 / (pairs * 2) as f64
+}
+
+/// +1 if the player moving first wins, -1 if the second one does, 0 for a full board.
+fn play_game(options: &Options, first: Weights, second: Weights, game: Game, pair: usize) -> i32 {
+    let mut session = Session::with_game(
+        Box::new(AiPlayer::with_weights(options.level, first)),
+        Box::new(AiPlayer::with_weights(options.level, second)),
+        game,
+    );
+    let mut ply = 0;
+    let mut log = |s: &Session| {
+        let last = &s.players[1 - s.game.next_player as usize];
+        if let Some(info) = last.search_info().filter(|_| options.verbose) {
+            println!("Pair {pair} ply {ply} {}: {info}", last.name());
+        }
+        ply += 1;
+    };
+    session.start_loop(&mut log);
+    log(&session);
+    match session.game.winner() {
+        Some(0) => 1,
+        Some(_) => -1,
+        None => 0,
+    }
+}
+
+fn random_opening(board_size: usize, seed: u64, pair: u64) -> Game {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed.wrapping_add(pair));
+    let mut game = Game::new(board_size);
+    for _ in 0..OPENING_PLIES {
+        if let Some(&m) = game.generate_moves().choose(&mut rng) {
+            game.apply_move(m).unwrap();
+        }
+        if game.game_over {
+            return random_opening(board_size, rng.gen(), 0);
+        }
+    }
+    game
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_weights() {
+        let args: Vec<String> = "--iterations 2 --games 4 --size 7 --seed 7 --threads 1"
+            .split(' ')
+            .map(String::from)
+            .collect();
+        let single = parse_args(&args).unwrap();
+        let parallel = Options {
+            threads: 2,
+            ..single.clone()
+        };
+        assert_eq!(single.games, 4);
+        assert_eq!(single.level, Level::Easy);
+        assert_eq!(tune(&single, |_, _, _| {}), tune(&parallel, |_, _, _| {}));
+    }
+}